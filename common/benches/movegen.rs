@@ -0,0 +1,55 @@
+//! Compares how many positions per second the `Board` based move generation and
+//! the `Bitboard` based move generation can visit. Run with `cargo bench`.
+
+extern crate common;
+extern crate rand;
+
+use common::*;
+use common::bitboard::Position;
+use common::perft::{perft, perft_position};
+
+use rand::{SeedableRng, StdRng};
+
+use std::time::Instant;
+
+const DEPTH: u32 = 6;
+
+fn report(name: &str, nodes: u64, start: Instant) {
+    let seconds = start.elapsed().as_secs_f64();
+
+    println!(
+        "{:>10}: {:>10} nodes in {:>7.3}s, {:>12.0} nodes/s",
+        name,
+        nodes,
+        seconds,
+        nodes as f64 / seconds
+    );
+}
+
+fn main() {
+    for &seed in [1, 2, 3].iter() {
+        let seed: &[_] = &[seed];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let game = GameState::deal(&mut rng);
+
+        println!(
+            "depth {} with {:?} {:?} {:?} {:?} {:?}",
+            DEPTH,
+            game.blue_cards.0,
+            game.blue_cards.1,
+            game.red_cards.0,
+            game.red_cards.1,
+            game.center_card
+        );
+
+        let start = Instant::now();
+        let board_nodes = perft(&game, DEPTH);
+        report("get_moves", board_nodes, start);
+
+        let start = Instant::now();
+        let bitboard_nodes = perft_position(&Position::from(&game), DEPTH);
+        report("bitboard", bitboard_nodes, start);
+
+        assert_eq!(board_nodes, bitboard_nodes);
+    }
+}
@@ -0,0 +1,89 @@
+//! Prints perft counts for a deal, for checking move generation by hand.
+//!
+//! usage: perft DEPTH [--divide] (--seed SEED | BLUE1 BLUE2 RED1 RED2 CENTER)
+
+extern crate common;
+extern crate rand;
+
+use common::*;
+use common::notation::Notation;
+use common::perft::{divide, perft};
+
+use rand::{SeedableRng, StdRng};
+
+use std::process;
+
+const USAGE: &str = "usage: perft DEPTH [--divide] (--seed SEED | BLUE1 BLUE2 RED1 RED2 CENTER)";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match run(&args) {
+        Ok(()) => {}
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(1);
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let depth: u32 = args.first()
+        .ok_or("no depth given")?
+        .parse()
+        .map_err(|e| format!("bad depth: {}", e))?;
+
+    let show_divide = args.iter().any(|a| a == "--divide");
+    let rest: Vec<&String> = args[1..].iter().filter(|a| *a != "--divide").collect();
+
+    let game = match rest.as_slice() {
+        [flag, seed] if *flag == "--seed" => {
+            let seed: usize = seed.parse().map_err(|e| format!("bad seed: {}", e))?;
+            let seed: &[_] = &[seed];
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+            GameState::deal(&mut rng)
+        }
+        [blue_1, blue_2, red_1, red_2, center] => {
+            let card = |s: &String| s.parse::<Card>().map_err(|e| e.to_string());
+            let center_card = card(center)?;
+
+            GameState {
+                board: starting_board(),
+                blue_cards: (card(blue_1)?, card(blue_2)?),
+                red_cards: (card(red_1)?, card(red_2)?),
+                center_card,
+                //the stamp on the center card decides who goes first, as when dealing
+                to_move: center_card.stamp(),
+            }
+        }
+        _ => return Err("expected either --seed SEED or five card names".to_string()),
+    };
+
+    println!(
+        "Blue: {} {}, Red: {} {}, center: {}, {} to move",
+        game.blue_cards.0,
+        game.blue_cards.1,
+        game.red_cards.0,
+        game.red_cards.1,
+        game.center_card,
+        game.to_move
+    );
+
+    if show_divide {
+        let mut total = 0;
+
+        for (ply, count) in divide(&game, depth) {
+            println!("{}: {}", Notation::new(&game, ply), count);
+            total += count;
+        }
+
+        println!("total: {}", total);
+    } else {
+        for d in 1..=depth {
+            println!("perft({}) = {}", d, perft(&game, d));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+//! Plays CPU players against each other without opening a window and prints
+//! how each did. With more than two players every pair plays a match and they
+//! are all given Elo ratings. Run with
+//! `cargo run --release --bin tournament -- strong-minimax casual-mcts`.
+
+extern crate common;
+
+use common::round_robin::{round_robin, RoundRobin};
+use common::tournament::{run_match, Entrant, MatchSettings};
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "usage: tournament [--games N] [--seed N] [--deck NAME] [--max-plies N] \
+[--csv PATH] [--markdown PATH] PLAYER PLAYER [PLAYER...]
+
+Each PLAYER is \"random\" or a difficulty and an engine, like strong-minimax or casual-mcts.
+Searching minimax players can be given their own evaluation weights after a colon, like
+strong-minimax:student=120,mobility=5. --games is how many games each pair plays.
+Without --seed the games are dealt from a seed based on the time, which is printed so
+the match can be played again. --csv and --markdown write the ratings to PATH.";
+
+struct Options {
+    settings: MatchSettings,
+    players: Vec<Entrant>,
+    csv: Option<PathBuf>,
+    markdown: Option<PathBuf>,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut settings = MatchSettings {
+        first_seed: time_seed(),
+        ..MatchSettings::default()
+    };
+    let mut players = Vec::new();
+    let mut csv = None;
+    let mut markdown = None;
+
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            players.push(arg.parse()?);
+            continue;
+        }
+
+        let value = args.next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+
+        match arg.as_str() {
+            "--games" => settings.games = number(&arg, &value)?,
+            "--seed" => settings.first_seed = number(&arg, &value)?,
+            "--deck" => settings.deck = value.parse()?,
+            "--max-plies" => settings.max_plies = number(&arg, &value)?,
+            "--csv" => csv = Some(PathBuf::from(value)),
+            "--markdown" => markdown = Some(PathBuf::from(value)),
+            _ => return Err(format!("there is no option called {}", arg)),
+        }
+    }
+
+    if players.len() < 2 {
+        return Err(format!("expected at least two players, not {}", players.len()));
+    }
+
+    Ok(Options {
+        settings,
+        players,
+        csv,
+        markdown,
+    })
+}
+
+fn number(flag: &str, value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, not \"{}\"", flag, value))
+}
+
+fn time_seed() -> usize {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as usize)
+        .unwrap_or(0)
+}
+
+//Plays the one match with a line for each game, so any game worth a closer
+//look can be found by its seed.
+fn play_match(players: &mut [Entrant], settings: &MatchSettings) -> RoundRobin {
+    let mut results = RoundRobin::new(players.iter().map(|p| p.name.clone()).collect());
+    let (a, b) = players.split_at_mut(1);
+    let name = a[0].name.clone();
+
+    let report = run_match(&mut a[0], &mut b[0], settings, |outcome| {
+        let result = match outcome.winner {
+            Some(winner) if winner == outcome.first_colour => "won",
+            Some(_) => "lost",
+            None => "drew",
+        };
+
+        println!(
+            "seed {}: {} {} as {} in {} plies",
+            outcome.seed, name, result, outcome.first_colour, outcome.plies
+        );
+    });
+
+    println!();
+    print!("{}", report);
+    results.add(0, 1, &report);
+
+    results
+}
+
+fn write_report(path: &Option<PathBuf>, contents: &str) {
+    if let Some(ref path) = *path {
+        if let Err(e) = fs::write(path, contents) {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let Options {
+        settings,
+        mut players,
+        csv,
+        markdown,
+    } = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    println!(
+        "{} games with the {} deck, seeds from {}",
+        settings.games, settings.deck, settings.first_seed
+    );
+
+    let results = if players.len() == 2 {
+        play_match(&mut players, &settings)
+    } else {
+        round_robin(&mut players, &settings, |report| println!("\n{}", report))
+    };
+
+    println!();
+    print!("{}", results.markdown());
+
+    write_report(&csv, &results.csv());
+    write_report(&markdown, &results.markdown());
+}
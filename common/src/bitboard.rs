@@ -0,0 +1,531 @@
+//! A compact version of `GameState` for code that needs to look at a lot of
+//! positions quickly, like searches. Bit `i` of a `Mask` is the square at
+//! `Board` index `i`, so converting between the two is cheap.
+
+use std::ops::{Deref, DerefMut};
+
+use ::*;
+use zobrist::{self, Hash};
+
+pub type Mask = u32;
+
+/// One mask per kind of piece, indexed by `piece as usize`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Bitboard {
+    pub pieces: [Mask; 5],
+}
+
+const PIECES: [Piece; 5] = [RedStudent, BlueStudent, RedMaster, BlueMaster, WindSpirit];
+
+impl Bitboard {
+    pub fn empty() -> Self {
+        Bitboard { pieces: [0; 5] }
+    }
+
+    pub fn from_board(board: &Board) -> Self {
+        let mut result = Bitboard::empty();
+
+        for (index, square) in board.iter().enumerate() {
+            if let Some(piece) = *square {
+                result.pieces[piece as usize] |= 1 << index;
+            }
+        }
+
+        result
+    }
+
+    pub fn to_board(&self) -> Board {
+        let mut board = [None; 25];
+
+        for (index, square) in board.iter_mut().enumerate() {
+            *square = self.piece_at(index);
+        }
+
+        board
+    }
+
+    pub fn piece_at(&self, index: usize) -> Option<Piece> {
+        let bit = 1 << index;
+
+        PIECES
+            .iter()
+            .find(|&&piece| self.pieces[piece as usize] & bit != 0)
+            .cloned()
+    }
+
+    pub fn occupied(&self, colour: PieceColour) -> Mask {
+        match colour {
+            Red => self.pieces[RedStudent as usize] | self.pieces[RedMaster as usize],
+            Blue => self.pieces[BlueStudent as usize] | self.pieces[BlueMaster as usize],
+        }
+    }
+
+    pub fn master(&self, colour: PieceColour) -> Mask {
+        match colour {
+            Red => self.pieces[RedMaster as usize],
+            Blue => self.pieces[BlueMaster as usize],
+        }
+    }
+
+    pub fn spirit(&self) -> Mask {
+        self.pieces[WindSpirit as usize]
+    }
+
+    /// Moves whatever is on `source_index` to `target_index`, removing anything
+    /// that was already there. This doesn't know the Wind Spirit's rules.
+    pub fn move_piece(&mut self, source_index: usize, target_index: usize) {
+        let source: Mask = 1 << source_index;
+        let target: Mask = 1 << target_index;
+
+        for mask in self.pieces.iter_mut() {
+            let moving = *mask & source != 0;
+
+            *mask &= !target;
+
+            if moving {
+                *mask = (*mask & !source) | target;
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a Board> for Bitboard {
+    fn from(board: &'a Board) -> Self {
+        Bitboard::from_board(board)
+    }
+}
+
+/// The squares a piece on a given square can move to with one card, indexed
+/// by `[colour as usize][square]`. This does not take other pieces into
+/// account.
+pub type CardTable = [[Mask; 25]; 2];
+
+/// The `CardTable` for each built-in card, indexed by `card.index()`.
+pub const MOVE_TABLE: [CardTable; CARD_COUNT] = build_move_table();
+
+const fn build_move_table() -> [CardTable; CARD_COUNT] {
+    let mut table = [[[0; 25]; 2]; CARD_COUNT];
+
+    let mut card_index = 0;
+    while card_index < CARD_COUNT {
+        table[card_index] = card_table(get_offsets(&CARDS[card_index]));
+
+        card_index += 1;
+    }
+
+    table
+}
+
+pub const fn card_table(offsets: &[(isize, isize)]) -> CardTable {
+    let mut table = [[0; 25]; 2];
+
+    let mut square = 0;
+    while square < 25 {
+        let x = (square % 5) as isize;
+        let y = (square / 5) as isize;
+
+        let mut i = 0;
+        while i < offsets.len() {
+            let (dx, dy) = offsets[i];
+
+            //Red sees the cards from the other side of the table
+            table[Red as usize][square] |= square_mask(x - dx, y - dy);
+            table[Blue as usize][square] |= square_mask(x + dx, y + dy);
+
+            i += 1;
+        }
+
+        square += 1;
+    }
+
+    table
+}
+
+const fn square_mask(x: isize, y: isize) -> Mask {
+    if x >= 0 && x < 5 && y >= 0 && y < 5 {
+        1 << (y * 5 + x)
+    } else {
+        0
+    }
+}
+
+const NO_MOVES: [Mask; 25] = [0; 25];
+
+pub fn move_table(card: Card, colour: PieceColour) -> &'static [Mask; 25] {
+    match card {
+        Card::Custom(i) => {
+            card_file::move_table(i).map_or(&NO_MOVES, |table| &table[colour as usize])
+        }
+        _ => &MOVE_TABLE[card.index()][colour as usize],
+    }
+}
+
+pub fn move_mask(card: Card, colour: PieceColour, square: usize) -> Mask {
+    move_table(card, colour)[square]
+}
+
+/// Each of the (at most five) pieces and the Wind Spirit can move with either
+/// card in up to `MAX_CARD_OFFSETS` ways, and a wind card can follow each
+/// piece move with up to `MAX_SPIRIT_OFFSETS` spirit moves.
+pub const MAX_PLIES: usize =
+    2 * ((5 + 1) * MAX_CARD_OFFSETS + 5 * MAX_CARD_OFFSETS * MAX_SPIRIT_OFFSETS);
+
+/// A fixed capacity list of plies so generating moves does not allocate.
+#[derive(Copy, Clone)]
+pub struct PlyList {
+    plies: [Ply; MAX_PLIES],
+    len: usize,
+}
+
+impl PlyList {
+    pub fn new() -> Self {
+        PlyList {
+            plies: [Ply::Pass(First); MAX_PLIES],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, ply: Ply) {
+        self.plies[self.len] = ply;
+        self.len += 1;
+    }
+}
+
+impl Default for PlyList {
+    fn default() -> Self {
+        PlyList::new()
+    }
+}
+
+impl Deref for PlyList {
+    type Target = [Ply];
+
+    fn deref(&self) -> &[Ply] {
+        &self.plies[..self.len]
+    }
+}
+
+impl DerefMut for PlyList {
+    fn deref_mut(&mut self) -> &mut [Ply] {
+        &mut self.plies[..self.len]
+    }
+}
+
+/// The same information as a `GameState`, with the board stored as a `Bitboard`
+/// and the Zobrist hash kept up to date as plies are applied.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Position {
+    pub bitboard: Bitboard,
+    pub blue_cards: (Card, Card),
+    pub red_cards: (Card, Card),
+    pub center_card: Card,
+    pub to_move: PieceColour,
+    pub hash: Hash,
+}
+
+impl Position {
+    pub fn to_game_state(&self) -> GameState {
+        GameState {
+            board: self.bitboard.to_board(),
+            blue_cards: self.blue_cards,
+            red_cards: self.red_cards,
+            center_card: self.center_card,
+            to_move: self.to_move,
+        }
+    }
+
+    pub fn cards(&self, colour: PieceColour) -> (Card, Card) {
+        match colour {
+            Red => self.red_cards,
+            Blue => self.blue_cards,
+        }
+    }
+
+    /// The squares that the pieces in `from` could move to using `colour`'s cards.
+    pub fn reach(&self, colour: PieceColour, from: Mask) -> Mask {
+        let cards = self.cards(colour);
+
+        let mut result = 0;
+
+        let mut sources = from;
+        while sources != 0 {
+            let source_index = sources.trailing_zeros() as usize;
+            sources &= sources - 1;
+
+            result |= move_mask(cards.0, colour, source_index) |
+                move_mask(cards.1, colour, source_index);
+        }
+
+        result & !self.bitboard.occupied(colour)
+    }
+
+    /// The squares that `colour` could move a piece to if it were their turn.
+    pub fn attacks(&self, colour: PieceColour) -> Mask {
+        self.reach(colour, self.bitboard.occupied(colour))
+    }
+
+    //Way of the Wind games go through `GameState`, which is slower but keeps
+    //the Wind Spirit's rules in one place.
+    fn uses_wind_rules(&self) -> bool {
+        self.bitboard.spirit() != 0 ||
+            [
+                self.blue_cards.0,
+                self.blue_cards.1,
+                self.red_cards.0,
+                self.red_cards.1,
+                self.center_card,
+            ].iter()
+                .any(Card::is_wind)
+    }
+
+    /// Equivalent to `GameState::legal_plies`, though not necessarily in the same order.
+    pub fn legal_plies(&self) -> PlyList {
+        let mut result = PlyList::new();
+
+        if self.uses_wind_rules() {
+            for ply in self.to_game_state().legal_plies() {
+                result.push(ply);
+            }
+
+            return result;
+        }
+
+        if self.outcome().is_some() {
+            return result;
+        }
+
+        let colour = self.to_move;
+        let own = self.bitboard.occupied(colour);
+        let cards = self.cards(colour);
+
+        for &(pair_index, card) in [(First, cards.0), (Second, cards.1)].iter() {
+            let table = move_table(card, colour);
+
+            let mut sources = own;
+            while sources != 0 {
+                let source_index = sources.trailing_zeros() as usize;
+                sources &= sources - 1;
+
+                let mut targets = table[source_index] & !own;
+                while targets != 0 {
+                    let target_index = targets.trailing_zeros() as usize;
+                    targets &= targets - 1;
+
+                    result.push(Ply::Move(
+                        Move {
+                            source_index,
+                            target_index,
+                        },
+                        pair_index,
+                    ));
+                }
+            }
+        }
+
+        if result.is_empty() {
+            result.push(Ply::Pass(First));
+            result.push(Ply::Pass(Second));
+        }
+
+        result
+    }
+
+    /// Equivalent to `GameState::apply`.
+    pub fn apply(&mut self, ply: Ply) {
+        if self.uses_wind_rules() {
+            let mut game = self.to_game_state();
+            game.apply(ply);
+
+            *self = Position::from(&game);
+            return;
+        }
+
+        let pair_index = match ply {
+            Ply::Move(m, pair_index) => {
+                if let Some(captured) = self.bitboard.piece_at(m.target_index) {
+                    self.hash ^= zobrist::piece_key(captured, m.target_index);
+                }
+                if let Some(moving) = self.bitboard.piece_at(m.source_index) {
+                    self.hash ^= zobrist::piece_key(moving, m.source_index) ^
+                        zobrist::piece_key(moving, m.target_index);
+                }
+
+                self.bitboard.move_piece(m.source_index, m.target_index);
+                pair_index
+            }
+            Ply::Wind(_, pair_index) | Ply::Pass(pair_index) => pair_index,
+        };
+
+        let colour = self.to_move;
+        let used_card = get_card(&self.cards(colour), pair_index);
+
+        self.hash ^= zobrist::card_key(used_card, Some(colour)) ^
+            zobrist::card_key(used_card, None) ^
+            zobrist::card_key(self.center_card, None) ^
+            zobrist::card_key(self.center_card, Some(colour));
+
+        let cards = match colour {
+            Red => &mut self.red_cards,
+            Blue => &mut self.blue_cards,
+        };
+        swap_cards(&mut self.center_card, cards, pair_index);
+
+        self.to_move = colour.opponent();
+        self.hash ^= zobrist::to_move_key(Red);
+    }
+
+    /// Equivalent to `GameState::outcome`.
+    pub fn outcome(&self) -> Option<PieceColour> {
+        let red_master = self.bitboard.master(Red);
+        let blue_master = self.bitboard.master(Blue);
+
+        if blue_master != 0 && (blue_master == 1 << TOP_PAGODA_INDEX || red_master == 0) {
+            Some(Blue)
+        } else if red_master != 0 && (red_master == 1 << BOTTOM_PAGODA_INDEX || blue_master == 0) {
+            Some(Red)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> From<&'a GameState> for Position {
+    fn from(game: &'a GameState) -> Self {
+        Position {
+            bitboard: Bitboard::from_board(&game.board),
+            blue_cards: game.blue_cards,
+            red_cards: game.red_cards,
+            center_card: game.center_card,
+            to_move: game.to_move,
+            hash: game.hash(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod matches_game_state {
+    use ::*;
+    use bitboard::*;
+    use rand::{Rng, SeedableRng};
+
+    fn sorted_plies(mut plies: Vec<Ply>) -> Vec<Ply> {
+        plies.sort_by_key(|ply| match *ply {
+            Ply::Move(m, pair_index) => (m.source_index, m.target_index, pair_index as usize, 0, 0),
+            Ply::Wind(w, pair_index) => {
+                let (piece, spirit) = (w.piece, w.spirit);
+
+                (
+                    piece.source_index,
+                    piece.target_index,
+                    pair_index as usize,
+                    spirit.source_index + 1,
+                    spirit.target_index,
+                )
+            }
+            Ply::Pass(pair_index) => (25, 25, pair_index as usize, 0, 0),
+        });
+
+        plies
+    }
+
+    #[test]
+    fn cards_are_in_declaration_order() {
+        for (i, card) in CARDS.iter().enumerate() {
+            assert_eq!(card.index(), i);
+        }
+    }
+
+    #[test]
+    fn wind_positions_with_many_plies_fit() {
+        let mut board = [None; 25];
+        for &index in [6, 13, 18, 21].iter() {
+            board[index] = Some(BlueStudent);
+        }
+        board[11] = Some(BlueMaster);
+        board[14] = Some(RedMaster);
+        board[23] = Some(WindSpirit);
+
+        let game = GameState {
+            board,
+            blue_cards: (Gale, Goose),
+            red_cards: (Frog, Eel),
+            center_card: Crab,
+            to_move: Blue,
+        };
+        let expected = game.legal_plies();
+
+        assert!(expected.len() > 5 * 2 * MAX_CARD_OFFSETS);
+        assert_eq!(
+            sorted_plies(Position::from(&game).legal_plies().to_vec()),
+            sorted_plies(expected)
+        );
+    }
+
+    #[test]
+    fn no_card_moves_the_spirit_more_ways_than_allowed() {
+        for card in Card::all_values() {
+            assert!(card.spirit_offsets().len() <= MAX_SPIRIT_OFFSETS, "{}", card);
+        }
+    }
+
+    #[test]
+    fn move_table_matches_valid_moves() {
+        let board = [None; 25];
+
+        for &card in CARDS.iter() {
+            for &colour in [Red, Blue].iter() {
+                for square in 0..25 {
+                    let expected = valid_moves(&board, &card, square, colour)
+                        .iter()
+                        .fold(0, |acc, m| acc | 1 << m.target_index);
+
+                    assert_eq!(move_mask(card, colour, square), expected);
+                }
+            }
+        }
+    }
+
+    quickcheck! {
+        fn random_games_agree(seed: usize) -> bool {
+            games_agree(seed, Deck::Base)
+        }
+
+        fn random_wind_games_agree(seed: usize) -> bool {
+            games_agree(seed, Deck::WayOfTheWind)
+        }
+    }
+
+    fn games_agree(seed: usize, deck: Deck) -> bool {
+        let seed: &[_] = &[seed];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+        let mut game = GameState::deal_from(&mut rng, deck);
+        let mut position = Position::from(&game);
+
+        //random games almost always end well before this
+        for _ in 0..1000 {
+            if Bitboard::from_board(&game.board).to_board() != game.board
+                || position.to_game_state() != game
+                || position.outcome() != game.outcome()
+            {
+                return false;
+            }
+
+            let plies = game.legal_plies();
+
+            if sorted_plies(plies.clone()) != sorted_plies(position.legal_plies().to_vec()) {
+                return false;
+            }
+
+            match rng.choose(&plies) {
+                Some(&ply) => {
+                    game.apply(ply);
+                    position.apply(ply);
+                }
+                None => return true,
+            }
+        }
+
+        true
+    }
+}
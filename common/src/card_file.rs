@@ -0,0 +1,348 @@
+//! House-rule cards, defined in a text file with one card per line:
+//!
+//! ```text
+//! # name | stamp | offsets
+//! Wolf | red | (0, -2) (-1, 1) (1, 1)
+//! ```
+//!
+//! Offsets are `(x, y)` from Blue's side of the board, like the ones in
+//! `Card::offsets`, so `(0, -1)` is one square forwards. Lines starting with
+//! `#` and blank lines are skipped.
+//!
+//! Once installed, custom cards are `Card::Custom`s and work everywhere the
+//! built-in cards do. They can only be installed once, before any game that
+//! uses them starts.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use ::*;
+use bitboard::{card_table, CardTable};
+
+/// Long enough for any of the built-in names, short enough to fit on a card.
+pub const MAX_NAME_LENGTH: usize = 16;
+/// Offsets go at most this far in each direction, so they fit on the 5x5 grid
+/// drawn on the cards.
+pub const MAX_REACH: isize = 2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CardDefinition {
+    pub name: String,
+    pub stamp: PieceColour,
+    pub offsets: Vec<(isize, isize)>,
+}
+
+impl fmt::Display for CardDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} | {} |", self.name, self.stamp)?;
+
+        for &(x, y) in self.offsets.iter() {
+            write!(f, " ({}, {})", x, y)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardFileError {
+    /// Which line of the file the problem is on, counting from one, if it is
+    /// on any one line.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for CardFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+fn error_at(line: usize, message: String) -> CardFileError {
+    CardFileError {
+        line: Some(line),
+        message,
+    }
+}
+
+//Any more and custom cards would run past the end of the Zobrist keys.
+fn check_count(count: usize) -> Result<(), CardFileError> {
+    if count > MAX_CUSTOM_CARDS {
+        Err(CardFileError {
+            line: None,
+            message: format!(
+                "there can be at most {} custom cards, not {}",
+                MAX_CUSTOM_CARDS,
+                count
+            ),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+pub fn load(path: &Path) -> Result<Vec<CardDefinition>, CardFileError> {
+    let text = fs::read_to_string(path).map_err(|e| {
+        CardFileError {
+            line: None,
+            message: format!("could not read {}: {}", path.display(), e),
+        }
+    })?;
+
+    parse(&text)
+}
+
+/// Reads and checks every card in `text`.
+pub fn parse(text: &str) -> Result<Vec<CardDefinition>, CardFileError> {
+    let mut result: Vec<CardDefinition> = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+
+        let (name, stamp, offsets) = match fields.as_slice() {
+            &[name, stamp, offsets] => (name, stamp, offsets),
+            _ => {
+                return Err(error_at(
+                    line_number,
+                    format!(
+                        "expected a name, a stamp and offsets separated by '|', like \"{}\"",
+                        "Wolf | red | (0, -2) (-1, 1) (1, 1)"
+                    ),
+                ))
+            }
+        };
+
+        let definition = CardDefinition {
+            name: name.to_string(),
+            stamp: PieceColour::from_name(stamp).ok_or_else(|| {
+                error_at(
+                    line_number,
+                    format!("expected red or blue for the stamp but found \"{}\"", stamp),
+                )
+            })?,
+            offsets: parse_offsets(offsets).map_err(|message| error_at(line_number, message))?,
+        };
+
+        check(&definition, &result).map_err(|message| error_at(line_number, message))?;
+
+        result.push(definition);
+    }
+
+    check_count(result.len())?;
+
+    Ok(result)
+}
+
+fn parse_offsets(text: &str) -> Result<Vec<(isize, isize)>, String> {
+    let bad_offsets = || {
+        format!(
+            "\"{}\" is not a list of offsets like \"(0, -2) (-1, 1)\"",
+            text
+        )
+    };
+
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+
+    let inner = compact
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+        .ok_or_else(bad_offsets)?;
+
+    inner
+        .split(")(")
+        .map(|pair| {
+            let mut split = pair.split(',');
+
+            match (split.next(), split.next(), split.next()) {
+                (Some(x), Some(y), None) => {
+                    match (x.parse(), y.parse()) {
+                        (Ok(x), Ok(y)) => Ok((x, y)),
+                        _ => Err(bad_offsets()),
+                    }
+                }
+                _ => Err(bad_offsets()),
+            }
+        })
+        .collect()
+}
+
+//Card names need to stay unambiguous in notation and in the `fen` module,
+//which leaves out spaces.
+fn check(definition: &CardDefinition, earlier: &[CardDefinition]) -> Result<(), String> {
+    let name = &definition.name;
+
+    if name.is_empty() {
+        return Err("the name is missing".to_string());
+    }
+
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "\"{}\" is longer than {} characters",
+            name,
+            MAX_NAME_LENGTH
+        ));
+    }
+
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ') {
+        return Err(format!(
+            "\"{}\" can only have letters, digits and spaces in it",
+            name
+        ));
+    }
+
+    let compact = |name: &str| name.replace(' ', "").to_ascii_lowercase();
+
+    let taken = CARDS
+        .iter()
+        .map(|card| card.name())
+        .chain(earlier.iter().map(|card| card.name.as_str()))
+        .find(|other| compact(other) == compact(name));
+
+    if let Some(other) = taken {
+        return Err(format!("\"{}\" is too much like the name of {}", name, other));
+    }
+
+    let offsets = &definition.offsets;
+
+    if offsets.len() > MAX_CARD_OFFSETS {
+        return Err(format!(
+            "{} has {} offsets but cards can have at most {}",
+            name,
+            offsets.len(),
+            MAX_CARD_OFFSETS
+        ));
+    }
+
+    for (i, &(x, y)) in offsets.iter().enumerate() {
+        if (x, y) == (0, 0) {
+            return Err(format!("{} has a (0, 0) offset, which is not a move", name));
+        }
+
+        if x.abs() > MAX_REACH || y.abs() > MAX_REACH {
+            return Err(format!(
+                "{}'s offset ({}, {}) goes more than {} squares away",
+                name,
+                x,
+                y,
+                MAX_REACH
+            ));
+        }
+
+        if offsets[..i].contains(&(x, y)) {
+            return Err(format!("{} has the offset ({}, {}) twice", name, x, y));
+        }
+    }
+
+    Ok(())
+}
+
+struct Installed {
+    definitions: Vec<CardDefinition>,
+    tables: Vec<CardTable>,
+}
+
+static INSTALLED: OnceLock<Installed> = OnceLock::new();
+
+/// Makes `definitions`, which should come from `parse` or `load`, available
+/// as `Card::Custom(0)`, `Card::Custom(1)` and so on. Installing a second
+/// time is an error, since it would change what any existing custom cards
+/// are, and so is installing more than `MAX_CUSTOM_CARDS`.
+pub fn install(definitions: Vec<CardDefinition>) -> Result<(), CardFileError> {
+    check_count(definitions.len())?;
+
+    let tables = definitions
+        .iter()
+        .map(|definition| card_table(&definition.offsets))
+        .collect();
+
+    INSTALLED
+        .set(Installed {
+            definitions,
+            tables,
+        })
+        .map_err(|_| {
+            CardFileError {
+                line: None,
+                message: "custom cards have already been installed".to_string(),
+            }
+        })
+}
+
+pub fn is_installed() -> bool {
+    INSTALLED.get().is_some()
+}
+
+pub fn definition(index: u8) -> Option<&'static CardDefinition> {
+    INSTALLED
+        .get()
+        .and_then(|installed| installed.definitions.get(index as usize))
+}
+
+pub fn move_table(index: u8) -> Option<&'static CardTable> {
+    INSTALLED
+        .get()
+        .and_then(|installed| installed.tables.get(index as usize))
+}
+
+pub fn custom_cards() -> Vec<Card> {
+    let count = INSTALLED
+        .get()
+        .map_or(0, |installed| installed.definitions.len());
+
+    (0..count).map(|i| Card::Custom(i as u8)).collect()
+}
+
+#[cfg(test)]
+mod checking {
+    use card_file::*;
+
+    fn error(text: &str) -> String {
+        parse(text).unwrap_err().message
+    }
+
+    #[test]
+    fn definitions_round_trip() {
+        let text = "# a comment\n\nWolf | red | (0, -2) (-1, 1) (1, 1)\nOwl|BLUE|(-1,-1)(1,-1)";
+        let definitions = parse(text).unwrap();
+
+        assert_eq!(
+            definitions[0],
+            CardDefinition {
+                name: "Wolf".to_string(),
+                stamp: Red,
+                offsets: vec![(0, -2), (-1, 1), (1, 1)],
+            }
+        );
+
+        let written: Vec<String> = definitions.iter().map(|d| d.to_string()).collect();
+        assert_eq!(parse(&written.join("\n")), Ok(definitions));
+    }
+
+    #[test]
+    fn bad_cards_are_rejected_with_the_line() {
+        assert_eq!(parse("Wolf | red | (0, 0)").unwrap_err().line, Some(1));
+        assert!(error("Wolf | red | (0, 0)").contains("(0, 0)"));
+        assert!(error("Wolf | red | (0, -3)").contains("more than 2"));
+        assert!(error("Wolf | red | (0, -1) (0, -1)").contains("twice"));
+        assert!(error("Wolf | red | (0,-1) (1,-1) (-1,-1) (1,0) (-1,0)").contains("at most 4"));
+        assert!(error("Wolf | green | (0, -1)").contains("green"));
+        assert!(error("Wolf | red").contains("separated by '|'"));
+        assert!(error("Wolf | red | 0, -1").contains("not a list of offsets"));
+        assert!(error("Tiger | red | (0, -1)").contains("Tiger"));
+        assert!(error("seasnake | red | (0, -1)").contains("Sea Snake"));
+        assert!(error("Wolf | red | (0, -1)\nWOLF | blue | (0, 1)").contains("Wolf"));
+        assert!(error("Wolf, Jr. | red | (0, -1)").contains("letters"));
+    }
+}
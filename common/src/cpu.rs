@@ -0,0 +1,621 @@
+//! How the CPU player picks its plies at each difficulty level.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use rand::{Rng, StdRng};
+
+use ::*;
+use bitboard::Position;
+use mcts::{Mcts, Rollout};
+use search::{priority, search, search_with_table, Limits, Priority, Weights};
+use transposition::TranspositionTable;
+
+/// Anything that can decide what to do on its turn.
+pub trait Agent {
+    /// Returns `None` if the game is already over.
+    fn choose_ply(&mut self, game: &GameState, rng: &mut StdRng) -> Option<Ply>;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Engine {
+    Minimax,
+    Mcts,
+}
+
+impl AllValues for Engine {
+    fn all_values() -> Vec<Engine> {
+        vec![Engine::Minimax, Engine::Mcts]
+    }
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Engine::Minimax => "Minimax",
+                Engine::Mcts => "MCTS",
+            }
+        )
+    }
+}
+
+impl Engine {
+    pub fn next(self) -> Engine {
+        match self {
+            Engine::Minimax => Engine::Mcts,
+            Engine::Mcts => Engine::Minimax,
+        }
+    }
+}
+
+/// Plain data describing a CPU player, so it can live in `State`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CpuPlayer {
+    pub engine: Engine,
+    pub difficulty: Difficulty,
+}
+
+impl Default for CpuPlayer {
+    fn default() -> Self {
+        CpuPlayer {
+            engine: Engine::Minimax,
+            difficulty: Difficulty::default(),
+        }
+    }
+}
+
+impl fmt::Display for CpuPlayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.difficulty, self.engine)
+    }
+}
+
+impl FromStr for CpuPlayer {
+    type Err = String;
+
+    /// Reads a difficulty followed by an engine, like "Strong Minimax" or
+    /// "casual-mcts", in any case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split(|c: char| c.is_whitespace() || c == '-' || c == ',')
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        match words.as_slice() {
+            [difficulty, engine] => Ok(CpuPlayer {
+                difficulty: named(difficulty, "difficulty")?,
+                engine: named(engine, "engine")?,
+            }),
+            _ => Err(format!(
+                "expected a difficulty and an engine like \"strong-minimax\", not \"{}\"",
+                s
+            )),
+        }
+    }
+}
+
+fn named<T: AllValues + fmt::Display>(name: &str, kind: &str) -> Result<T, String> {
+    T::all_values()
+        .into_iter()
+        .find(|value| value.to_string().eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("there is no {} called \"{}\"", kind, name))
+}
+
+/// A `Cpu` choosing a ply on another thread, so whatever is waiting for it can
+/// carry on in the meantime.
+pub struct Thinking {
+    /// The position the CPU is choosing a ply for.
+    pub game: GameState,
+    receiver: Receiver<(Cpu, Option<Ply>)>,
+}
+
+impl Thinking {
+    pub fn start(mut cpu: Cpu, game: GameState, mut rng: StdRng) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let ply = cpu.choose_ply(&game, &mut rng);
+
+            //nobody is waiting any more if this fails, so there's nothing to do
+            let _ = sender.send((cpu, ply));
+        });
+
+        Thinking { game, receiver }
+    }
+
+    /// Returns the CPU and the ply it chose once it has finished, or `None`
+    /// while it is still thinking.
+    pub fn poll(&self) -> Option<(Cpu, Option<Ply>)> {
+        match self.receiver.try_recv() {
+            Ok(finished) => Some(finished),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                panic!("the CPU stopped thinking without choosing a ply")
+            }
+        }
+    }
+}
+
+/// A minimax CPU player that evaluates positions with its own weights, for
+/// finding out whether a change to the evaluation makes it stronger.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tuned {
+    pub difficulty: Difficulty,
+    pub weights: Weights,
+}
+
+impl Tuned {
+    /// Fails if `player` would never evaluate a position.
+    pub fn new(player: CpuPlayer, weights: Weights) -> Result<Tuned, String> {
+        match (player.engine, player.difficulty.settings().strategy) {
+            (Engine::Minimax, Strategy::Search(_)) => Ok(Tuned {
+                difficulty: player.difficulty,
+                weights,
+            }),
+            _ => Err(format!("{} does not use evaluation weights", player)),
+        }
+    }
+}
+
+impl fmt::Display for Tuned {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}:{}", self.difficulty, Engine::Minimax, self.weights)
+    }
+}
+
+impl FromStr for Tuned {
+    type Err = String;
+
+    /// Reads a `CpuPlayer` and some `Weights` separated by a colon, like
+    /// "strong-minimax:mobility=5".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut halves = s.splitn(2, ':');
+        let player = halves.next().unwrap_or("").parse()?;
+        let weights = halves.next().unwrap_or("").parse()?;
+
+        Tuned::new(player, weights)
+    }
+}
+
+/// A CPU player as it plays. Unlike `CpuPlayer` this keeps what its searches
+/// have found out from one ply to the next, so each search starts out knowing
+/// what the last one learned.
+pub struct Cpu {
+    pub player: CpuPlayer,
+    pub weights: Weights,
+    table: TranspositionTable,
+}
+
+impl Cpu {
+    pub fn new(player: CpuPlayer) -> Self {
+        Cpu {
+            player,
+            weights: Weights::default(),
+            table: TranspositionTable::default(),
+        }
+    }
+}
+
+impl From<CpuPlayer> for Cpu {
+    fn from(player: CpuPlayer) -> Self {
+        Cpu::new(player)
+    }
+}
+
+impl From<Tuned> for Cpu {
+    fn from(tuned: Tuned) -> Self {
+        Cpu {
+            weights: tuned.weights,
+            ..Cpu::new(CpuPlayer {
+                engine: Engine::Minimax,
+                difficulty: tuned.difficulty,
+            })
+        }
+    }
+}
+
+impl fmt::Display for Cpu {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.weights == Weights::default() {
+            write!(f, "{}", self.player)
+        } else {
+            write!(f, "{}:{}", self.player, self.weights)
+        }
+    }
+}
+
+impl Agent for Cpu {
+    fn choose_ply(&mut self, game: &GameState, rng: &mut StdRng) -> Option<Ply> {
+        if game.must_pass() {
+            return pass_ply(game);
+        }
+
+        let difficulty = self.player.difficulty;
+
+        match self.player.engine {
+            Engine::Minimax => {
+                choose_searched_ply(game, difficulty, &self.weights, &mut self.table, rng)
+            }
+            Engine::Mcts => difficulty.mcts().choose_ply(game, rng),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Difficulty {
+    Beginner,
+    Casual,
+    #[default]
+    Strong,
+    Max,
+}
+use self::Difficulty::*;
+
+impl AllValues for Difficulty {
+    fn all_values() -> Vec<Difficulty> {
+        vec![Beginner, Casual, Strong, Max]
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Beginner => "Beginner",
+                Casual => "Casual",
+                Strong => "Strong",
+                Max => "Max",
+            }
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Strategy {
+    /// Only looks one ply ahead, preferring captures and safe moves.
+    Heuristic,
+    Search(Limits),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CpuSettings {
+    pub strategy: Strategy,
+    /// The chance of ignoring the strategy and playing a random ply instead.
+    pub blunder_rate: f32,
+}
+
+impl Difficulty {
+    pub fn settings(self) -> CpuSettings {
+        match self {
+            Beginner => CpuSettings {
+                strategy: Strategy::Search(Limits {
+                    max_depth: 1,
+                    time_budget: Some(Duration::from_millis(50)),
+                }),
+                blunder_rate: 0.35,
+            },
+            Casual => CpuSettings {
+                strategy: Strategy::Heuristic,
+                blunder_rate: 0.0,
+            },
+            Strong => CpuSettings {
+                strategy: Strategy::Search(Limits {
+                    max_depth: 6,
+                    time_budget: Some(Duration::from_millis(500)),
+                }),
+                blunder_rate: 0.02,
+            },
+            Max => CpuSettings {
+                strategy: Strategy::Search(Limits {
+                    max_depth: 64,
+                    time_budget: Some(Duration::from_millis(2000)),
+                }),
+                blunder_rate: 0.0,
+            },
+        }
+    }
+
+    /// The Monte Carlo equivalent of this difficulty.
+    pub fn mcts(self) -> Mcts {
+        let (iterations, time_budget, rollout) = match self {
+            Beginner => (200, None, Rollout::Random),
+            Casual => (1000, None, Rollout::Random),
+            Strong => (5000, Some(Duration::from_millis(500)), Rollout::Heuristic),
+            Max => (50000, Some(Duration::from_millis(2000)), Rollout::Heuristic),
+        };
+
+        Mcts {
+            iterations,
+            time_budget,
+            rollout,
+            ..Mcts::default()
+        }
+    }
+
+    /// The next difficulty up, wrapping around to the easiest.
+    pub fn next(self) -> Difficulty {
+        match self {
+            Beginner => Casual,
+            Casual => Strong,
+            Strong => Max,
+            Max => Beginner,
+        }
+    }
+}
+
+/// Returns `None` if the game is already over.
+pub fn choose_ply<R: Rng>(game: &GameState, difficulty: Difficulty, rng: &mut R) -> Option<Ply> {
+    choose_searched_ply(
+        game,
+        difficulty,
+        &Weights::default(),
+        &mut TranspositionTable::default(),
+        rng,
+    )
+}
+
+/// Like `choose_ply`, but searching difficulties evaluate positions with
+/// `weights` and use and update `table`.
+pub fn choose_searched_ply<R: Rng>(
+    game: &GameState,
+    difficulty: Difficulty,
+    weights: &Weights,
+    table: &mut TranspositionTable,
+    rng: &mut R,
+) -> Option<Ply> {
+    if game.must_pass() {
+        return pass_ply(game);
+    }
+
+    let plies = game.legal_plies();
+
+    if plies.is_empty() {
+        return None;
+    }
+
+    let settings = difficulty.settings();
+
+    if rng.gen::<f32>() < settings.blunder_rate {
+        return rng.choose(&plies).cloned();
+    }
+
+    match settings.strategy {
+        Strategy::Heuristic => heuristic_ply(game, rng),
+        Strategy::Search(limits) => {
+            search_with_table(game, &limits, weights, table).map(|r| r.ply)
+        }
+    }
+}
+
+const PASS_SEARCH_DEPTH: u32 = 4;
+
+/// Picks which card to give up when there are no moves, by searching a few
+/// plies ahead with each one. Even the weaker difficulty levels use this, since
+/// giving the opponent a card at random is a needlessly bad way to lose.
+/// Returns `None` unless `game.must_pass()`.
+pub fn pass_ply(game: &GameState) -> Option<Ply> {
+    if !game.must_pass() {
+        return None;
+    }
+
+    let limits = Limits {
+        max_depth: PASS_SEARCH_DEPTH,
+        time_budget: None,
+    };
+
+    search(game, &limits, &Weights::default()).map(|r| r.ply)
+}
+
+/// Win now if possible, otherwise take a capture the opponent can't answer,
+/// otherwise randomly favour either captures or safe moves, while avoiding
+/// moves that let the opponent win immediately wherever possible.
+pub fn heuristic_ply<R: Rng>(game: &GameState, rng: &mut R) -> Option<Ply> {
+    let position = Position::from(game);
+
+    let prioritized: Vec<(Ply, Priority)> = position
+        .legal_plies()
+        .iter()
+        .map(|&ply| (ply, priority(&position, ply)))
+        .collect();
+
+    let of_priority = |wanted: Priority| -> Vec<Ply> {
+        prioritized
+            .iter()
+            .filter(|&&(_, p)| p == wanted)
+            .map(|&(ply, _)| ply)
+            .collect()
+    };
+
+    let agressive = rng.gen::<bool>();
+
+    let (first_priority, second_priority) = if agressive {
+        (Priority::Capture, Priority::Safe)
+    } else {
+        (Priority::Safe, Priority::Capture)
+    };
+
+    let viable: Vec<Ply> = prioritized
+        .iter()
+        .filter(|&&(_, p)| p != Priority::Losing)
+        .map(|&(ply, _)| ply)
+        .collect();
+
+    let all: Vec<Ply> = prioritized.iter().map(|&(ply, _)| ply).collect();
+
+    for candidates in [
+        of_priority(Priority::Winning),
+        of_priority(Priority::UndefendedCapture),
+        of_priority(first_priority),
+        of_priority(second_priority),
+        viable,
+        all,
+    ].iter()
+    {
+        if let Some(&ply) = rng.choose(candidates) {
+            return Some(ply);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod choosing {
+    use ::*;
+    use cpu::*;
+    use fixtures::{lone_masters, rng};
+
+    #[test]
+    fn every_difficulty_takes_an_immediate_win_without_blundering() {
+        let game = lone_masters(7, 20);
+        let mut rng = rng();
+
+        for difficulty in Difficulty::all_values() {
+            if difficulty.settings().blunder_rate > 0.0 {
+                continue;
+            }
+
+            let mut next = game;
+            next.apply(choose_ply(&game, difficulty, &mut rng).unwrap());
+
+            assert_eq!(next.outcome(), Some(Blue), "{}", difficulty);
+        }
+    }
+
+    #[test]
+    fn heuristic_avoids_an_immediate_loss() {
+        let game = lone_masters(12, TOP_PAGODA_INDEX);
+        let mut rng = rng();
+
+        for _ in 0..20 {
+            let mut next = game;
+            next.apply(heuristic_ply(&game, &mut rng).unwrap());
+
+            assert!(next.legal_plies().iter().all(|&ply| {
+                let mut after = next;
+                after.apply(ply);
+                after.outcome() != Some(Red)
+            }));
+        }
+    }
+
+    /// Blue's pieces fill the right column, so neither Tiger nor Ox can move
+    /// any of them. Giving up Ox would let Red's master step down into Blue's
+    /// pagoda two plies later, but giving up Tiger would not.
+    fn stuck() -> GameState {
+        let mut board = [None; 25];
+        board[0] = Some(RedStudent);
+        board[17] = Some(RedMaster);
+        for &index in [4, 9, 14, 19].iter() {
+            board[index] = Some(BlueStudent);
+        }
+        board[24] = Some(BlueMaster);
+
+        GameState {
+            board,
+            blue_cards: (Tiger, Ox),
+            red_cards: (Monkey, Mantis),
+            center_card: Frog,
+            to_move: Blue,
+        }
+    }
+
+    #[test]
+    fn passing_only_happens_when_stuck() {
+        assert!(stuck().must_pass());
+        assert_eq!(pass_ply(&lone_masters(12, 2)), None);
+    }
+
+    #[test]
+    fn every_cpu_chooses_its_pass_card_by_evaluation() {
+        let game = stuck();
+        let mut rng = rng();
+
+        for difficulty in Difficulty::all_values() {
+            for engine in Engine::all_values() {
+                let mut cpu = Cpu::new(CpuPlayer { engine, difficulty });
+
+                for _ in 0..10 {
+                    assert_eq!(
+                        cpu.choose_ply(&game, &mut rng),
+                        Some(Ply::Pass(First)),
+                        "{}",
+                        cpu
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn searching_cpus_remember_what_they_found() {
+        let game = lone_masters(12, 2);
+        let mut cpu = Cpu::new(CpuPlayer {
+            engine: Engine::Minimax,
+            difficulty: Max,
+        });
+
+        cpu.choose_ply(&game, &mut rng());
+
+        assert!(cpu.table.probe(Position::from(&game).hash).is_some());
+    }
+
+    #[test]
+    fn thinking_finishes_with_a_legal_ply() {
+        let game = lone_masters(12, 2);
+        let cpu = Cpu::new(CpuPlayer::default());
+
+        let thinking = Thinking::start(cpu, game, rng());
+        let finished = loop {
+            if let Some(finished) = thinking.poll() {
+                break finished;
+            }
+
+            std::thread::sleep(Duration::from_millis(1));
+        };
+
+        assert!(game.legal_plies().contains(&finished.1.unwrap()));
+        assert_eq!(finished.0.player, CpuPlayer::default());
+    }
+
+    #[test]
+    fn next_visits_every_difficulty() {
+        let all = Difficulty::all_values();
+
+        for (i, &difficulty) in all.iter().enumerate() {
+            assert_eq!(difficulty.next(), all[(i + 1) % all.len()]);
+        }
+    }
+
+    #[test]
+    fn cpu_players_are_read_by_name() {
+        let casual_mcts = CpuPlayer {
+            engine: Engine::Mcts,
+            difficulty: Casual,
+        };
+
+        assert_eq!("casual-mcts".parse(), Ok(casual_mcts));
+        assert_eq!("Casual MCTS".parse(), Ok(casual_mcts));
+        assert_eq!(casual_mcts.to_string().parse(), Ok(casual_mcts));
+        assert!("casual".parse::<CpuPlayer>().is_err());
+        assert!("hard minimax".parse::<CpuPlayer>().is_err());
+    }
+
+    #[test]
+    fn only_searching_minimax_players_can_be_tuned() {
+        let tuned: Tuned = "beginner-minimax:student=120".parse().unwrap();
+
+        assert_eq!(tuned.difficulty, Beginner);
+        assert_eq!(tuned.weights.student, 120);
+        assert_eq!(tuned.to_string().parse(), Ok(tuned));
+        assert!("casual-minimax:student=120".parse::<Tuned>().is_err());
+        assert!("strong-mcts:student=120".parse::<Tuned>().is_err());
+    }
+}
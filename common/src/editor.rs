@@ -0,0 +1,167 @@
+//! Setting up a position by hand to play from.
+
+use ::*;
+
+/// One of the five places a card can be.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CardSlot {
+    Blue(PairIndex),
+    Red(PairIndex),
+    Center,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Editor {
+    /// May not be a legal setup until `finish` says it is.
+    pub game: GameState,
+    /// Why the last attempt to finish failed, if it did.
+    pub problem: Option<String>,
+}
+
+/// The order clicking a square goes through.
+const SQUARE_CYCLE: [Option<Piece>; 6] = [
+    None,
+    Some(BlueStudent),
+    Some(BlueMaster),
+    Some(RedStudent),
+    Some(RedMaster),
+    Some(WindSpirit),
+];
+
+impl Editor {
+    pub fn new(game: GameState) -> Self {
+        Editor {
+            game,
+            problem: None,
+        }
+    }
+
+    /// Puts the next piece in `SQUARE_CYCLE` on the square, ending with
+    /// removing it.
+    pub fn cycle_square(&mut self, index: usize) {
+        if let Some(square) = self.game.board.get_mut(index) {
+            let position = SQUARE_CYCLE
+                .iter()
+                .position(|&piece| piece == *square)
+                .unwrap_or(0);
+
+            *square = SQUARE_CYCLE[(position + 1) % SQUARE_CYCLE.len()];
+            self.problem = None;
+        }
+    }
+
+    pub fn card(&self, slot: CardSlot) -> Card {
+        match slot {
+            CardSlot::Blue(pair_index) => self.game.card(Blue, pair_index),
+            CardSlot::Red(pair_index) => self.game.card(Red, pair_index),
+            CardSlot::Center => self.game.center_card,
+        }
+    }
+
+    /// Swaps the card in `slot` for the one after it in `Card::all_values()`.
+    pub fn cycle_card(&mut self, slot: CardSlot) {
+        let cards = Card::all_values();
+        let position = cards
+            .iter()
+            .position(|&card| card == self.card(slot))
+            .unwrap_or(0);
+        let next = cards[(position + 1) % cards.len()];
+
+        match slot {
+            CardSlot::Blue(PairIndex::First) => self.game.blue_cards.0 = next,
+            CardSlot::Blue(PairIndex::Second) => self.game.blue_cards.1 = next,
+            CardSlot::Red(PairIndex::First) => self.game.red_cards.0 = next,
+            CardSlot::Red(PairIndex::Second) => self.game.red_cards.1 = next,
+            CardSlot::Center => self.game.center_card = next,
+        }
+        self.problem = None;
+    }
+
+    pub fn toggle_to_move(&mut self) {
+        self.game.to_move = self.game.to_move.opponent();
+        self.problem = None;
+    }
+
+    /// The position to play from, if it is one a game could have. Otherwise
+    /// `problem` says what is wrong with it.
+    pub fn finish(&mut self) -> Option<GameState> {
+        match self.game.check_setup() {
+            Ok(()) => Some(self.game),
+            Err(problem) => {
+                self.problem = Some(problem);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod editing {
+    use editor::*;
+
+    fn editor() -> Editor {
+        Editor::new(GameState {
+            board: starting_board(),
+            blue_cards: (Tiger, Crab),
+            red_cards: (Monkey, Frog),
+            center_card: Rabbit,
+            to_move: Blue,
+        })
+    }
+
+    #[test]
+    fn clicking_a_square_goes_through_every_piece_and_back() {
+        let mut editor = editor();
+
+        for &expected in SQUARE_CYCLE[1..].iter().chain(SQUARE_CYCLE[..1].iter()) {
+            editor.cycle_square(12);
+            assert_eq!(editor.game.board[12], expected);
+        }
+    }
+
+    #[test]
+    fn cards_cycle_through_every_card() {
+        let mut editor = editor();
+        let mut seen = Vec::new();
+
+        for _ in Card::all_values() {
+            editor.cycle_card(CardSlot::Center);
+            seen.push(editor.card(CardSlot::Center));
+        }
+
+        assert_eq!(seen.last(), Some(&Rabbit));
+        for card in Card::all_values() {
+            assert!(seen.contains(&card));
+        }
+    }
+
+    #[test]
+    fn illegal_setups_are_rejected_with_a_reason() {
+        let mut editor = editor();
+        assert_eq!(editor.finish(), Some(editor.game));
+
+        //the blue master
+        editor.cycle_square(22);
+        editor.cycle_square(22);
+        editor.cycle_square(22);
+        assert_eq!(editor.finish(), None);
+        assert!(editor.problem.as_ref().unwrap().contains("master"));
+
+        let mut editor = self::editor();
+        //a second red master
+        editor.cycle_square(12);
+        editor.cycle_square(12);
+        editor.cycle_square(12);
+        editor.cycle_square(12);
+        assert_eq!(editor.finish(), None);
+        assert!(editor.problem.as_ref().unwrap().contains("master"));
+
+        let mut editor = self::editor();
+        editor.game.center_card = Tiger;
+        assert_eq!(editor.finish(), None);
+        assert!(editor.problem.as_ref().unwrap().contains("Tiger"));
+
+        editor.toggle_to_move();
+        assert_eq!(editor.problem, None);
+    }
+}
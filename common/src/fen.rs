@@ -0,0 +1,265 @@
+//! A one line text form of a position, in the style of chess's FEN:
+//!
+//! ```text
+//! rrRrr/5/5/5/bbBbb Tiger,Crab Monkey,Frog Rabbit b
+//! ```
+//!
+//! That is the board from the top row down with `/` between rows, digits for
+//! runs of empty squares and `r`, `R`, `b`, `B` for red and blue students and
+//! masters, or `w` for the Wind Spirit, then Blue's cards, Red's cards, the
+//! center card and `b` or `r` for whoever moves next. Spaces are left out of
+//! card names.
+
+use std::fmt;
+use std::str::FromStr;
+
+use ::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FenError(pub String);
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+fn compact_name(card: Card) -> String {
+    card.name().replace(' ', "")
+}
+
+fn parse_card(name: &str) -> Result<Card, FenError> {
+    Card::all_values()
+        .into_iter()
+        .find(|&card| compact_name(card).eq_ignore_ascii_case(name))
+        .ok_or_else(|| FenError(UnknownCard(name.to_string()).to_string()))
+}
+
+fn parse_pair(field: &str) -> Result<(Card, Card), FenError> {
+    let mut split = field.split(',');
+
+    match (split.next(), split.next(), split.next()) {
+        (Some(first), Some(second), None) => Ok((parse_card(first)?, parse_card(second)?)),
+        _ => Err(FenError(format!(
+            "expected two cards separated by a comma, like \"Tiger,Crab\", but found \"{}\"",
+            field
+        ))),
+    }
+}
+
+fn parse_board(field: &str) -> Result<Board, FenError> {
+    let rows: Vec<&str> = field.split('/').collect();
+
+    if rows.len() != 5 {
+        return Err(FenError(format!(
+            "expected five rows separated by '/' but found {}",
+            rows.len()
+        )));
+    }
+
+    let mut board = [None; 25];
+
+    for (y, row) in rows.iter().enumerate() {
+        let mut x = 0;
+
+        for c in row.chars() {
+            if let Some(empty) = c.to_digit(10) {
+                x += empty as usize;
+            } else {
+                let piece = Piece::from_letter(c).ok_or_else(|| {
+                    FenError(format!("'{}' is not a piece, expected one of r R b B w", c))
+                })?;
+
+                if x < 5 {
+                    board[y * 5 + x] = Some(piece);
+                }
+                x += 1;
+            }
+        }
+
+        if x != 5 {
+            return Err(FenError(format!(
+                "row {} (\"{}\") covers {} squares instead of 5",
+                y + 1,
+                row,
+                x
+            )));
+        }
+    }
+
+    Ok(board)
+}
+
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (y, row) in self.board.chunks(5).enumerate() {
+            if y > 0 {
+                write!(f, "/")?;
+            }
+
+            let mut empty = 0;
+
+            for square in row {
+                match *square {
+                    Some(piece) => {
+                        if empty > 0 {
+                            write!(f, "{}", empty)?;
+                            empty = 0;
+                        }
+
+                        write!(f, "{}", piece.letter())?;
+                    }
+                    None => empty += 1,
+                }
+            }
+
+            if empty > 0 {
+                write!(f, "{}", empty)?;
+            }
+        }
+
+        write!(
+            f,
+            " {},{} {},{} {} {}",
+            compact_name(self.blue_cards.0),
+            compact_name(self.blue_cards.1),
+            compact_name(self.red_cards.0),
+            compact_name(self.red_cards.1),
+            compact_name(self.center_card),
+            match self.to_move {
+                Blue => 'b',
+                Red => 'r',
+            }
+        )
+    }
+}
+
+impl FromStr for GameState {
+    type Err = FenError;
+
+    /// Also rejects positions that could not come up in a game, according to
+    /// `GameState::check_position`. Positions from games that are over are
+    /// allowed, so they can be copied from the UI and read back.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+
+        let (board, blue, red, center, to_move) = match fields.as_slice() {
+            &[board, blue, red, center, to_move] => (board, blue, red, center, to_move),
+            _ => {
+                return Err(FenError(format!(
+                    "expected five parts separated by spaces, like \"{}\", but found {}",
+                    "rrRrr/5/5/5/bbBbb Tiger,Crab Monkey,Frog Rabbit b",
+                    fields.len()
+                )))
+            }
+        };
+
+        let game = GameState {
+            board: parse_board(board)?,
+            blue_cards: parse_pair(blue)?,
+            red_cards: parse_pair(red)?,
+            center_card: parse_card(center)?,
+            to_move: match to_move {
+                "b" | "B" => Blue,
+                "r" | "R" => Red,
+                _ => {
+                    return Err(FenError(format!(
+                        "expected b or r for who moves next but found \"{}\"",
+                        to_move
+                    )))
+                }
+            },
+        };
+
+        game.check_position().map_err(FenError)?;
+
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod one_line {
+    use fen::*;
+    use rand::{Rng, SeedableRng, StdRng};
+
+    quickcheck! {
+        fn positions_round_trip(seed: usize) -> bool {
+            let seed: &[_] = &[seed];
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+            let mut game = GameState::deal(&mut rng);
+
+            for _ in 0..200 {
+                if game.outcome().is_some() {
+                    break;
+                }
+
+                if game.to_string().parse() != Ok(game) {
+                    return false;
+                }
+
+                match rng.choose(&game.legal_plies()) {
+                    Some(&ply) => game.apply(ply),
+                    None => break,
+                }
+            }
+
+            true
+        }
+    }
+
+    #[test]
+    fn the_starting_position_looks_like_the_example() {
+        let game = GameState {
+            board: starting_board(),
+            blue_cards: (Tiger, Crab),
+            red_cards: (Monkey, Frog),
+            center_card: Rabbit,
+            to_move: Blue,
+        };
+
+        assert_eq!(
+            game.to_string(),
+            "rrRrr/5/5/5/bbBbb Tiger,Crab Monkey,Frog Rabbit b"
+        );
+    }
+
+    #[test]
+    fn the_wind_spirit_is_a_w() {
+        let text = "rrRrr/5/2w2/5/bbBbb Gust,Crab Monkey,Zephyr Rabbit b";
+        let game: GameState = text.parse().unwrap();
+
+        assert_eq!(game.board[CENTER_INDEX], Some(WindSpirit));
+        assert_eq!(game.to_string(), text);
+        assert!("rrRrr/5/1w1w1/5/bbBbb Gust,Crab Monkey,Zephyr Rabbit b"
+            .parse::<GameState>()
+            .is_err());
+    }
+
+    #[test]
+    fn finished_games_round_trip() {
+        let captured: GameState = "rr1rr/2B2/5/5/bb1bb Tiger,Crab Monkey,Frog Rabbit r"
+            .parse()
+            .unwrap();
+        let on_the_pagoda: GameState = "rrBrr/5/5/5/bbRbb Tiger,Crab Monkey,Frog Rabbit r"
+            .parse()
+            .unwrap();
+
+        for &game in [captured, on_the_pagoda].iter() {
+            assert!(game.outcome().is_some());
+            assert_eq!(game.to_string().parse(), Ok(game));
+        }
+    }
+
+    #[test]
+    fn mistakes_are_explained() {
+        let error = |s: &str| s.parse::<GameState>().unwrap_err().0;
+
+        assert!(error("rrRrr/5/5/5/bbBbb Tiger,Crab Monkey,Frog Rabbit").contains("five parts"));
+        assert!(error("rrRrr/5/5/bbBbb Tiger,Crab Monkey,Frog Rabbit b").contains("five rows"));
+        assert!(error("rrRrr/6/5/5/bbBbb Tiger,Crab Monkey,Frog Rabbit b").contains("row 2"));
+        assert!(error("rrRrr/5/5/5/bbBbb Tiger,Crab Monkey,Frog Robin b").contains("Robin"));
+        assert!(error("rrRrR/5/5/5/bbBbb Tiger,Crab Monkey,Frog Rabbit b").contains("master"));
+        assert!(error("rrrr1/5/5/5/bbbb1 Tiger,Crab Monkey,Frog Rabbit b").contains("master"));
+        assert!(error("rrRrr/5/5/5/bbBbb Tiger,Tiger Monkey,Frog Rabbit b").contains("Tiger"));
+    }
+}
@@ -0,0 +1,25 @@
+//! Positions and helpers shared by the unit tests of the CPU players.
+
+use rand::StdRng;
+
+use ::*;
+
+/// Always the same, so tests that use randomness are repeatable.
+pub fn rng() -> StdRng {
+    seeded_rng(42)
+}
+
+/// Just the two masters on an otherwise empty board, with Blue to move.
+pub fn lone_masters(blue_master: usize, red_master: usize) -> GameState {
+    let mut board = [None; 25];
+    board[blue_master] = Some(BlueMaster);
+    board[red_master] = Some(RedMaster);
+
+    GameState {
+        board,
+        blue_cards: (Boar, Ox),
+        red_cards: (Tiger, Crab),
+        center_card: Frog,
+        to_move: Blue,
+    }
+}
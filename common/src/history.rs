@@ -0,0 +1,279 @@
+//! Everything that has been played so far in a game, with enough information
+//! to take plies back and play them again.
+
+use ::*;
+
+/// One ply along with what it changed, so it can be undone.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Record {
+    pub colour: PieceColour,
+    pub ply: Ply,
+    /// The card that was moved with, or given up when passing.
+    pub card: Card,
+    /// The Wind Spirit swaps places with what it moves onto rather than
+    /// capturing it, so this is only ever one of the players' pieces.
+    pub captured: Option<Piece>,
+    pub previous_board: Board,
+    /// The mover's cards before the ply.
+    pub previous_cards: (Card, Card),
+    pub previous_center_card: Card,
+}
+
+impl GameState {
+    /// What `apply`ing `ply` would change. This does not check that the ply
+    /// is legal.
+    pub fn record(&self, ply: Ply) -> Record {
+        let (captured, pair_index) = match ply {
+            Ply::Move(m, pair_index) => {
+                if self.board[m.source_index] == Some(WindSpirit) {
+                    (None, pair_index)
+                } else {
+                    (self.board[m.target_index], pair_index)
+                }
+            }
+            Ply::Wind(wind_move, pair_index) => {
+                (self.board[wind_move.piece.target_index], pair_index)
+            }
+            Ply::Pass(pair_index) => (None, pair_index),
+        };
+
+        Record {
+            colour: self.to_move,
+            ply,
+            card: self.card(self.to_move, pair_index),
+            captured,
+            previous_board: self.board,
+            previous_cards: self.cards(self.to_move),
+            previous_center_card: self.center_card,
+        }
+    }
+
+    /// Takes back the ply that `record` was made from. This is only correct
+    /// if that was the last ply applied.
+    pub fn unapply(&mut self, record: &Record) {
+        self.board = record.previous_board;
+        *self.cards_mut(record.colour) = record.previous_cards;
+        self.center_card = record.previous_center_card;
+        self.to_move = record.colour;
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct History {
+    records: Vec<Record>,
+    /// Plies that have been undone, with the most recently undone last.
+    undone: Vec<Record>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Applies `ply` to `game` and remembers it. Anything that was undone can
+    /// no longer be redone afterwards.
+    pub fn play(&mut self, game: &mut GameState, ply: Ply) {
+        self.records.push(game.record(ply));
+        self.undone.clear();
+
+        game.apply(ply);
+    }
+
+    /// Returns the record of the ply that was taken back, if there was one.
+    pub fn undo(&mut self, game: &mut GameState) -> Option<Record> {
+        let record = self.records.pop()?;
+
+        game.unapply(&record);
+        self.undone.push(record);
+
+        Some(record)
+    }
+
+    /// Returns the record of the ply that was played again, if there was one.
+    pub fn redo(&mut self, game: &mut GameState) -> Option<Record> {
+        let record = self.undone.pop()?;
+
+        game.apply(record.ply);
+        self.records.push(record);
+
+        Some(record)
+    }
+
+    /// Undoes plies until `colour` is back to making the last ply they made,
+    /// so the opponent's reply is taken back along with it. Does nothing if
+    /// `colour` has not made any plies yet.
+    pub fn undo_turn(&mut self, game: &mut GameState, colour: PieceColour) -> bool {
+        if !self.records.iter().any(|r| r.colour == colour) {
+            return false;
+        }
+
+        while let Some(record) = self.undo(game) {
+            if record.colour == colour {
+                break;
+            }
+        }
+
+        true
+    }
+
+    /// Redoes the next ply by `colour` and the opponent's reply to it, if
+    /// they were undone.
+    pub fn redo_turn(&mut self, game: &mut GameState, colour: PieceColour) -> bool {
+        match self.undone.last() {
+            Some(record) if record.colour == colour => {}
+            _ => return false,
+        }
+
+        self.redo(game);
+
+        if game.to_move != colour {
+            self.redo(game);
+        }
+
+        true
+    }
+
+    /// How the game looked after the first `plies` plies, given that `game`
+    /// is how it looks now.
+    pub fn position_after(&self, game: &GameState, plies: usize) -> GameState {
+        let mut result = *game;
+
+        for record in self.records.iter().skip(plies).rev() {
+            result.unapply(record);
+        }
+
+        result
+    }
+
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.records.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod undoing {
+    use history::*;
+    use rand::{Rng, SeedableRng, StdRng};
+
+    fn random_game(seed: usize, plies: usize) -> (GameState, GameState, History) {
+        random_game_from(seed, plies, Deck::Base)
+    }
+
+    fn random_game_from(
+        seed: usize,
+        plies: usize,
+        deck: Deck,
+    ) -> (GameState, GameState, History) {
+        let seed: &[_] = &[seed];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+        let start = GameState::deal_from(&mut rng, deck);
+        let mut game = start;
+        let mut history = History::new();
+
+        for _ in 0..plies {
+            match rng.choose(&game.legal_plies()) {
+                Some(&ply) => history.play(&mut game, ply),
+                None => break,
+            }
+        }
+
+        (start, game, history)
+    }
+
+    quickcheck! {
+        fn undoing_everything_gets_back_to_the_start(seed: usize) -> bool {
+            let (start, mut game, mut history) = random_game(seed, 100);
+
+            while history.undo(&mut game).is_some() {}
+
+            game == start
+        }
+
+        fn undoing_wind_moves_puts_the_spirit_back(seed: usize) -> bool {
+            let (start, mut game, mut history) = random_game_from(seed, 100, Deck::WayOfTheWind);
+
+            while history.undo(&mut game).is_some() {}
+
+            game == start
+        }
+
+        fn redoing_everything_gets_back_to_the_end(seed: usize) -> bool {
+            let (_, end, mut history) = random_game(seed, 100);
+            let mut game = end;
+
+            while history.undo(&mut game).is_some() {}
+            while history.redo(&mut game).is_some() {}
+
+            game == end
+        }
+    }
+
+    #[test]
+    fn position_after_matches_undoing() {
+        let (start, game, history) = random_game(42, 10);
+
+        assert_eq!(history.position_after(&game, 0), start);
+        assert_eq!(history.position_after(&game, history.len()), game);
+
+        let mut undone = game;
+        let mut partial = history.clone();
+        partial.undo(&mut undone);
+        partial.undo(&mut undone);
+
+        assert_eq!(history.position_after(&game, history.len() - 2), undone);
+    }
+
+    #[test]
+    fn playing_after_undoing_forgets_the_undone_plies() {
+        let (_, mut game, mut history) = random_game(42, 10);
+
+        history.undo(&mut game);
+        assert!(history.can_redo());
+
+        let ply = game.legal_plies()[0];
+        history.play(&mut game, ply);
+
+        assert!(!history.can_redo());
+        assert_eq!(history.len(), 10);
+    }
+
+    #[test]
+    fn undo_turn_takes_back_the_reply_too() {
+        let (_, mut game, mut history) = random_game(42, 10);
+        let colour = game.to_move;
+        let before = game;
+
+        assert!(history.undo_turn(&mut game, colour));
+        assert_eq!(history.len(), 8);
+        assert_eq!(game.to_move, colour);
+
+        assert!(history.redo_turn(&mut game, colour));
+        assert_eq!(game, before);
+    }
+
+    #[test]
+    fn undo_turn_does_nothing_before_the_first_ply() {
+        let (_, mut game, mut history) = random_game(42, 1);
+        let colour = game.to_move;
+
+        assert!(!history.undo_turn(&mut game, colour));
+        assert_eq!(history.len(), 1);
+    }
+}
@@ -1,8 +1,30 @@
 extern crate rand;
 
 use std::fmt;
+use std::str::FromStr;
 
-use rand::{Rand, Rng, StdRng};
+pub mod bitboard;
+pub mod card_file;
+pub mod cpu;
+pub mod editor;
+pub mod fen;
+#[cfg(test)]
+mod fixtures;
+pub mod history;
+pub mod mcts;
+pub mod notation;
+pub mod perft;
+pub mod record;
+pub mod replay;
+pub mod round_robin;
+pub mod save;
+pub mod search;
+pub mod spectate;
+pub mod tournament;
+pub mod transposition;
+pub mod zobrist;
+
+use rand::{Rand, Rng, SeedableRng, StdRng};
 
 pub struct Platform {
     pub print_xy: fn(i32, i32, &str),
@@ -25,11 +47,46 @@ pub struct Platform {
 
 pub struct State {
     pub rng: StdRng,
-    pub board: Board,
-    pub player_cards: (Card, Card),
-    pub center_card: Card,
-    pub cpu_cards: (Card, Card),
+    /// What `rng` was seeded with at the start of the game.
+    pub seed: usize,
+    pub game: GameState,
     pub turn: Turn,
+    pub history: history::History,
+    /// How many plies into the history to show instead of the live game, if
+    /// any.
+    pub preview: Option<usize>,
+    /// How many entries the history panel is scrolled up from the bottom.
+    pub history_scroll: usize,
+    /// When this is set the screen shows the replay instead of the game.
+    pub replay: Option<replay::Replay>,
+    /// When this is set the screen shows the position editor instead of the
+    /// game.
+    pub editor: Option<editor::Editor>,
+    pub cpu: cpu::CpuPlayer,
+    /// Plays Blue when two CPUs play each other, with `cpu` playing Red.
+    pub blue_cpu: cpu::CpuPlayer,
+    /// The CPU playing each colour, indexed by `PieceColour as usize`, kept
+    /// from one ply to the next so it remembers what its searches found out.
+    /// Made from `cpu` or `blue_cpu` on their first ply.
+    pub cpu_agents: [Option<cpu::Cpu>; 2],
+    /// Set while a CPU is choosing its ply, which happens on another thread so
+    /// the UI keeps responding.
+    pub thinking: Option<cpu::Thinking>,
+    pub mode: Mode,
+    /// Which colour the player takes against the CPU in the next new game.
+    pub side: Side,
+    /// In hot-seat games, whether to turn the board and cards around so
+    /// whoever is to move sees them from their own side.
+    pub flip_board: bool,
+    /// How fast a game between two CPUs goes, and whether it is paused.
+    pub spectator: spectate::Spectator,
+    /// Which cards the next new game is dealt from.
+    pub deck: Deck,
+    /// The card that started in the center, kept until the player's first ply
+    /// so the UI can say why whoever went first did.
+    pub first_move_card: Option<Card>,
+    /// Shown to the player until they next pick a card.
+    pub message: Option<String>,
     pub show_credits: bool,
     pub rotate_opponet_cards: bool,
     pub ui_context: UIContext,
@@ -40,6 +97,12 @@ pub enum Turn {
     Waiting,
     SelectedCard(PairIndex),
     SelectedPiece(PairIndex, usize),
+    /// The player has picked where a wind card sends their piece and now
+    /// picks where the Wind Spirit goes.
+    MovingSpirit(PairIndex, Move),
+    /// The player has no moves so must pick which card to exchange with the
+    /// center card.
+    MustPass,
     CpuTurn,
     Over(PieceColour),
 }
@@ -63,7 +126,7 @@ pub trait AllValues {
         Self: std::marker::Sized;
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Card {
     Tiger,
     Crab,
@@ -81,12 +144,134 @@ pub enum Card {
     Rooster,
     Ox,
     Cobra,
+    //Sensei's Path
+    Fox,
+    Dog,
+    Giraffe,
+    Panda,
+    Bear,
+    Kirin,
+    SeaSnake,
+    Viper,
+    Phoenix,
+    Mouse,
+    Rat,
+    Turtle,
+    Tanuki,
+    Iguana,
+    Sable,
+    Otter,
+    //Way of the Wind
+    Gust,
+    Gale,
+    Breeze,
+    Zephyr,
+    /// One of the cards installed with `card_file::install`.
+    Custom(u8),
 }
 use Card::*;
 
 impl Card {
-    pub fn as_str(&self, rotate: bool) -> &'static str {
-        match (*self, rotate) {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Tiger => "Tiger",
+            Crab => "Crab",
+            Monkey => "Monkey",
+            Crane => "Crane",
+            Dragon => "Dragon",
+            Elephant => "Elephant",
+            Mantis => "Mantis",
+            Boar => "Boar",
+            Frog => "Frog",
+            Goose => "Goose",
+            Horse => "Horse",
+            Eel => "Eel",
+            Rabbit => "Rabbit",
+            Rooster => "Rooster",
+            Ox => "Ox",
+            Cobra => "Cobra",
+            Fox => "Fox",
+            Dog => "Dog",
+            Giraffe => "Giraffe",
+            Panda => "Panda",
+            Bear => "Bear",
+            Kirin => "Kirin",
+            SeaSnake => "Sea Snake",
+            Viper => "Viper",
+            Phoenix => "Phoenix",
+            Mouse => "Mouse",
+            Rat => "Rat",
+            Turtle => "Turtle",
+            Tanuki => "Tanuki",
+            Iguana => "Iguana",
+            Sable => "Sable",
+            Otter => "Otter",
+            Gust => "Gust",
+            Gale => "Gale",
+            Breeze => "Breeze",
+            Zephyr => "Zephyr",
+            Custom(i) => card_file::definition(i).map_or("?", |card| card.name.as_str()),
+        }
+    }
+
+    /// Where the card is in `CARDS`, or after all of them for custom cards.
+    pub const fn index(&self) -> usize {
+        match *self {
+            Tiger => 0,
+            Crab => 1,
+            Monkey => 2,
+            Crane => 3,
+            Dragon => 4,
+            Elephant => 5,
+            Mantis => 6,
+            Boar => 7,
+            Frog => 8,
+            Goose => 9,
+            Horse => 10,
+            Eel => 11,
+            Rabbit => 12,
+            Rooster => 13,
+            Ox => 14,
+            Cobra => 15,
+            Fox => 16,
+            Dog => 17,
+            Giraffe => 18,
+            Panda => 19,
+            Bear => 20,
+            Kirin => 21,
+            SeaSnake => 22,
+            Viper => 23,
+            Phoenix => 24,
+            Mouse => 25,
+            Rat => 26,
+            Turtle => 27,
+            Tanuki => 28,
+            Iguana => 29,
+            Sable => 30,
+            Otter => 31,
+            Gust => 32,
+            Gale => 33,
+            Breeze => 34,
+            Zephyr => 35,
+            Custom(i) => CARD_COUNT + i as usize,
+        }
+    }
+
+    /// The colour of the stamp in the corner of the card. Whoever's colour is
+    /// on the starting center card moves first.
+    pub fn stamp(&self) -> PieceColour {
+        match *self {
+            Dragon | Frog | Elephant | Rooster | Mantis | Horse | Boar | Cobra | Fox | Panda |
+            Kirin | Viper | Rat | Turtle | Iguana | Otter | Gale | Breeze => Red,
+            Tiger | Rabbit | Crab | Goose | Monkey | Ox | Crane | Eel | Dog | Giraffe | Bear |
+            SeaSnake | Phoenix | Mouse | Tanuki | Sable | Gust | Zephyr => Blue,
+            Custom(i) => card_file::definition(i).map_or(Blue, |card| card.stamp),
+        }
+    }
+
+    /// The tile for the card's picture in `cards.png`, if it has one.
+    pub fn as_str(&self, rotate: bool) -> Option<&'static str> {
+        Some(match (*self, rotate) {
             (Tiger, false) => "\u{E0C0}",
             (Tiger, true) => "\u{E0D0}",
             (Crab, false) => "\u{E0C1}",
@@ -119,30 +304,318 @@ impl Card {
             (Ox, true) => "\u{E0DE}",
             (Cobra, false) => "\u{E0CF}",
             (Cobra, true) => "\u{E0DF}",
+            _ => return None,
+        })
+    }
+
+    /// Wind cards move one of the player's pieces and then the Wind Spirit,
+    /// instead of moving just one piece.
+    pub fn is_wind(&self) -> bool {
+        !self.spirit_offsets().is_empty()
+    }
+
+    /// Where a wind card moves the Wind Spirit after moving a piece, in the
+    /// same form as `offsets`. Empty for every other card.
+    pub fn spirit_offsets(&self) -> &'static [(isize, isize)] {
+        match *self {
+            Gust => &[(0, -1), (0, 1)],
+            Gale => &[(-1, 0), (1, 0)],
+            Breeze => &[(1, -1), (-1, 1)],
+            Zephyr => &[(-1, -1), (1, 1)],
+            _ => &[],
+        }
+    }
+
+    /// The squares the card allows moving to, relative to the piece, from
+    /// Blue's side of the board. Negative `y`s are forwards. For wind cards
+    /// these are the moves for the player's piece.
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        match *self {
+            Custom(i) => card_file::definition(i).map_or(&[], |card| card.offsets.as_slice()),
+            _ => get_offsets(self),
         }
     }
 }
 
+/// Every built-in card, in declaration order, so `CARDS[card.index()] == card`.
+pub const CARDS: [Card; CARD_COUNT] = [
+    Tiger,
+    Crab,
+    Monkey,
+    Crane,
+    Dragon,
+    Elephant,
+    Mantis,
+    Boar,
+    Frog,
+    Goose,
+    Horse,
+    Eel,
+    Rabbit,
+    Rooster,
+    Ox,
+    Cobra,
+    Fox,
+    Dog,
+    Giraffe,
+    Panda,
+    Bear,
+    Kirin,
+    SeaSnake,
+    Viper,
+    Phoenix,
+    Mouse,
+    Rat,
+    Turtle,
+    Tanuki,
+    Iguana,
+    Sable,
+    Otter,
+    Gust,
+    Gale,
+    Breeze,
+    Zephyr,
+];
+pub const CARD_COUNT: usize = 36;
+/// The cards from the base game come first in `CARDS`, followed by the ones
+/// from the Sensei's Path expansion and then the Way of the Wind ones.
+pub const BASE_CARD_COUNT: usize = 16;
+pub const WIND_CARD_COUNT: usize = 4;
+const SENSEIS_PATH_CARDS: std::ops::Range<usize> = BASE_CARD_COUNT..CARD_COUNT - WIND_CARD_COUNT;
+/// How many cards `card_file::install` can add on top of `CARDS`.
+pub const MAX_CUSTOM_CARDS: usize = 32;
+/// No card, built-in or custom, allows more moves than this.
+pub const MAX_CARD_OFFSETS: usize = 4;
+/// No wind card moves the Wind Spirit in more ways than this.
+pub const MAX_SPIRIT_OFFSETS: usize = 2;
+
 impl AllValues for Card {
+    /// The built-in cards followed by any custom ones.
     fn all_values() -> Vec<Card> {
-        vec![
-            Tiger,
-            Crab,
-            Monkey,
-            Crane,
-            Dragon,
-            Elephant,
-            Mantis,
-            Boar,
-            Frog,
-            Goose,
-            Horse,
-            Eel,
-            Rabbit,
-            Rooster,
-            Ox,
-            Cobra,
-        ]
+        let mut result = CARDS.to_vec();
+        result.extend(card_file::custom_cards());
+        result
+    }
+}
+
+/// Which cards get shuffled together at the start of a game.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Deck {
+    #[default]
+    Base,
+    SenseisPath,
+    /// The base game's and Sensei's Path's cards, along with any custom ones.
+    Mixed,
+    /// The base game's cards and the wind cards, with the Wind Spirit in the
+    /// middle of the board.
+    WayOfTheWind,
+}
+
+impl Deck {
+    pub fn cards(&self) -> Vec<Card> {
+        match *self {
+            Deck::Base => CARDS[..BASE_CARD_COUNT].to_vec(),
+            Deck::SenseisPath => CARDS[SENSEIS_PATH_CARDS].to_vec(),
+            Deck::Mixed => {
+                Card::all_values()
+                    .into_iter()
+                    .filter(|card| !card.is_wind())
+                    .collect()
+            }
+            Deck::WayOfTheWind => {
+                let mut cards = CARDS[..BASE_CARD_COUNT].to_vec();
+                cards.extend_from_slice(&CARDS[SENSEIS_PATH_CARDS.end..]);
+                cards
+            }
+        }
+    }
+
+    pub fn next(self) -> Deck {
+        match self {
+            Deck::Base => Deck::SenseisPath,
+            Deck::SenseisPath => Deck::Mixed,
+            Deck::Mixed => Deck::WayOfTheWind,
+            Deck::WayOfTheWind => Deck::Base,
+        }
+    }
+
+    pub fn has_wind_spirit(&self) -> bool {
+        *self == Deck::WayOfTheWind
+    }
+}
+
+impl AllValues for Deck {
+    fn all_values() -> Vec<Deck> {
+        vec![Deck::Base, Deck::SenseisPath, Deck::Mixed, Deck::WayOfTheWind]
+    }
+}
+
+/// Who plays each side.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mode {
+    /// The player has the given colour and the CPU has the other one.
+    VsCpu(PieceColour),
+    /// Two people take turns at the same computer.
+    HotSeat,
+    /// Two CPUs play each other while the player watches.
+    Spectate,
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::VsCpu(Blue)
+    }
+}
+
+impl Mode {
+    pub fn is_cpu(&self, colour: PieceColour) -> bool {
+        match *self {
+            Mode::VsCpu(player) => colour != player,
+            Mode::HotSeat => false,
+            Mode::Spectate => true,
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Mode::VsCpu(_) => "vs CPU",
+                Mode::HotSeat => "Hot seat",
+                Mode::Spectate => "CPU vs CPU",
+            }
+        )
+    }
+}
+
+/// Which colour the player asks to play against the CPU.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Side {
+    #[default]
+    Blue,
+    Red,
+    /// Picked when the game starts.
+    Random,
+}
+
+impl Side {
+    pub fn colour<R: Rng>(self, rng: &mut R) -> PieceColour {
+        match self {
+            Side::Blue => Blue,
+            Side::Red => Red,
+            Side::Random => if rng.gen::<bool>() { Blue } else { Red },
+        }
+    }
+
+    pub fn next(self) -> Side {
+        match self {
+            Side::Blue => Side::Red,
+            Side::Red => Side::Random,
+            Side::Random => Side::Blue,
+        }
+    }
+}
+
+impl AllValues for Side {
+    fn all_values() -> Vec<Side> {
+        vec![Side::Blue, Side::Red, Side::Random]
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Side::Blue => "Blue",
+                Side::Red => "Red",
+                Side::Random => "Random",
+            }
+        )
+    }
+}
+
+impl FromStr for Side {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Side::all_values()
+            .into_iter()
+            .find(|side| side.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("there is no side called \"{}\", expected blue, red or random", s)
+            })
+    }
+}
+
+impl fmt::Display for Deck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Deck::Base => "Base",
+                Deck::SenseisPath => "Sensei's Path",
+                Deck::Mixed => "Mixed",
+                Deck::WayOfTheWind => "Way of the Wind",
+            }
+        )
+    }
+}
+
+impl FromStr for Deck {
+    type Err = String;
+
+    /// Accepts "expansion" for Sensei's Path and "wind" for Way of the Wind as
+    /// well as the names from `Display`, in any case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("expansion") {
+            return Ok(Deck::SenseisPath);
+        }
+        if s.eq_ignore_ascii_case("wind") {
+            return Ok(Deck::WayOfTheWind);
+        }
+
+        Deck::all_values()
+            .into_iter()
+            .find(|deck| deck.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "there is no deck called \"{}\", expected base, expansion, mixed or wind",
+                    s
+                )
+            })
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownCard(pub String);
+
+impl fmt::Display for UnknownCard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "there is no card called \"{}\"", self.0)
+    }
+}
+
+impl FromStr for Card {
+    type Err = UnknownCard;
+
+    /// Accepts the names from `Card::name` in any case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Card::all_values()
+            .into_iter()
+            .find(|card| card.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownCard(s.to_string()))
     }
 }
 
@@ -164,7 +637,7 @@ pub fn valid_move_locations(
 
     if is_index_on_board(piece_index) {
         if let Some((x, y)) = get_board_xy(piece_index) {
-            for &(mut x_1, mut y_1) in get_offsets(card).iter() {
+            for &(mut x_1, mut y_1) in card.offsets().iter() {
                 if piece_colour == Red {
                     x_1 *= -1;
                     y_1 *= -1;
@@ -173,7 +646,7 @@ pub fn valid_move_locations(
                 let result_x = (x as isize).wrapping_add(x_1) as usize;
                 let result_y = (y as isize).wrapping_add(y_1) as usize;
                 if let Some(target_index) = get_board_index(result_x, result_y) {
-                    if board[target_index].map(Piece::colour) != Some(piece_colour) {
+                    if can_move_onto(board[target_index], piece_colour) {
                         result.push((result_x, result_y));
                     }
                 }
@@ -194,7 +667,7 @@ pub fn valid_moves(
 
     if is_index_on_board(piece_index) {
         if let Some((x, y)) = get_board_xy(piece_index) {
-            for &(mut x_1, mut y_1) in get_offsets(card).iter() {
+            for &(mut x_1, mut y_1) in card.offsets().iter() {
                 if piece_colour == Red {
                     x_1 *= -1;
                     y_1 *= -1;
@@ -203,7 +676,7 @@ pub fn valid_moves(
                 let result_x = (x as isize).wrapping_add(x_1) as usize;
                 let result_y = (y as isize).wrapping_add(y_1) as usize;
                 if let Some(target_index) = get_board_index(result_x, result_y) {
-                    if board[target_index].map(Piece::colour) != Some(piece_colour) {
+                    if can_move_onto(board[target_index], piece_colour) {
                         result.push(Move {
                             source_index: piece_index,
                             target_index,
@@ -217,24 +690,97 @@ pub fn valid_moves(
     result
 }
 
-fn get_offsets(card: &Card) -> Vec<(isize, isize)> {
+/// Pieces can capture the other side's pieces but not their own, or the Wind
+/// Spirit.
+pub fn can_move_onto(square: Option<Piece>, colour: PieceColour) -> bool {
+    match square {
+        None => true,
+        Some(piece) => piece.colour() == Some(colour.opponent()),
+    }
+}
+
+/// Where the Wind Spirit on `spirit_index` can go using `offsets`, which are
+/// seen from `colour`'s side of the board. The spirit cannot capture, so it
+/// can only move onto empty squares or swap places with a student.
+pub fn spirit_moves(
+    board: &Board,
+    offsets: &[(isize, isize)],
+    spirit_index: usize,
+    colour: PieceColour,
+) -> Vec<Move> {
+    let mut result = Vec::new();
+
+    if let Some((x, y)) = get_board_xy(spirit_index) {
+        for &(mut x_1, mut y_1) in offsets.iter() {
+            if colour == Red {
+                x_1 *= -1;
+                y_1 *= -1;
+            }
+
+            let result_x = (x as isize).wrapping_add(x_1) as usize;
+            let result_y = (y as isize).wrapping_add(y_1) as usize;
+            if let Some(target_index) = get_board_index(result_x, result_y) {
+                match board[target_index] {
+                    None | Some(RedStudent) | Some(BlueStudent) => {
+                        result.push(Move {
+                            source_index: spirit_index,
+                            target_index,
+                        });
+                    }
+                    Some(RedMaster) | Some(BlueMaster) | Some(WindSpirit) => {}
+                }
+            }
+        }
+    }
+
+    result
+}
+
+pub fn get_spirit_index(board: &Board) -> Option<usize> {
+    board.iter().position(|p| *p == Some(WindSpirit))
+}
+
+//Only knows the built-in cards, so it can be used to build tables at compile
+//time. `Card::offsets` knows about custom ones too.
+const fn get_offsets(card: &Card) -> &'static [(isize, isize)] {
     match *card {
-        Tiger => vec![(0, -2), (0, 1)],
-        Crab => vec![(-2, 0), (2, 0), (0, -1)],
-        Monkey => vec![(-1, -1), (-1, 1), (1, -1), (1, 1)],
-        Crane => vec![(-1, 1), (1, 1), (0, -1)],
-        Dragon => vec![(-1, 1), (1, 1), (-2, -1), (2, -1)],
-        Elephant => vec![(-1, 0), (1, 0), (-1, -1), (1, -1)],
-        Mantis => vec![(-1, -1), (1, -1), (0, 1)],
-        Boar => vec![(-1, 0), (1, 0), (0, -1)],
-        Frog => vec![(-2, 0), (-1, -1), (1, 1)],
-        Goose => vec![(-1, 0), (-1, -1), (1, 0), (1, 1)],
-        Horse => vec![(-1, 0), (0, -1), (0, 1)],
-        Eel => vec![(1, 0), (-1, -1), (-1, 1)],
-        Rabbit => vec![(2, 0), (1, -1), (-1, 1)],
-        Rooster => vec![(1, 0), (1, -1), (-1, 0), (-1, 1)],
-        Ox => vec![(1, 0), (0, -1), (0, 1)],
-        Cobra => vec![(-1, 0), (1, -1), (1, 1)],
+        Tiger => &[(0, -2), (0, 1)],
+        Crab => &[(-2, 0), (2, 0), (0, -1)],
+        Monkey => &[(-1, -1), (-1, 1), (1, -1), (1, 1)],
+        Crane => &[(-1, 1), (1, 1), (0, -1)],
+        Dragon => &[(-1, 1), (1, 1), (-2, -1), (2, -1)],
+        Elephant => &[(-1, 0), (1, 0), (-1, -1), (1, -1)],
+        Mantis => &[(-1, -1), (1, -1), (0, 1)],
+        Boar => &[(-1, 0), (1, 0), (0, -1)],
+        Frog => &[(-2, 0), (-1, -1), (1, 1)],
+        Goose => &[(-1, 0), (-1, -1), (1, 0), (1, 1)],
+        Horse => &[(-1, 0), (0, -1), (0, 1)],
+        Eel => &[(1, 0), (-1, -1), (-1, 1)],
+        Rabbit => &[(2, 0), (1, -1), (-1, 1)],
+        Rooster => &[(1, 0), (1, -1), (-1, 0), (-1, 1)],
+        Ox => &[(1, 0), (0, -1), (0, 1)],
+        Cobra => &[(-1, 0), (1, -1), (1, 1)],
+        Fox => &[(1, -1), (1, 0), (1, 1)],
+        Dog => &[(-1, -1), (-1, 0), (-1, 1)],
+        Giraffe => &[(-2, -1), (2, -1), (0, 1)],
+        Panda => &[(0, -1), (1, -1), (-1, 1)],
+        Bear => &[(0, -1), (-1, -1), (1, 1)],
+        Kirin => &[(-1, -2), (1, -2), (0, 2)],
+        SeaSnake => &[(0, -1), (2, 0), (-1, 1)],
+        Viper => &[(0, -1), (-2, 0), (1, 1)],
+        Phoenix => &[(-1, -1), (1, -1), (-2, 0), (2, 0)],
+        Mouse => &[(0, -1), (1, 0), (-1, 1)],
+        Rat => &[(0, -1), (-1, 0), (1, 1)],
+        Turtle => &[(-2, 0), (2, 0), (-1, 1), (1, 1)],
+        Tanuki => &[(0, -1), (2, -1), (-1, 1)],
+        Iguana => &[(0, -1), (-2, -1), (1, 1)],
+        Sable => &[(1, -1), (-2, 0), (-1, 1)],
+        Otter => &[(-1, -1), (2, 0), (1, 1)],
+        Gust => &[(-1, -1), (1, -1)],
+        Gale => &[(-1, -1), (1, -1)],
+        Breeze => &[(0, -1), (-1, 0)],
+        Zephyr => &[(0, -1), (1, 0)],
+        Custom(_) => &[],
     }
 }
 
@@ -309,28 +855,59 @@ fn xy_on_board(x: usize, y: usize) -> bool {
 }
 
 pub const TOP_PAGODA_INDEX: usize = 2;
+/// Where the Wind Spirit starts.
+pub const CENTER_INDEX: usize = 12;
 pub const BOTTOM_PAGODA_INDEX: usize = 22;
 
-#[derive(Copy, Clone, Debug)]
+/// The index of the pagoda that `colour`'s master is trying to reach.
+pub fn goal_index(colour: PieceColour) -> usize {
+    match colour {
+        Red => BOTTOM_PAGODA_INDEX,
+        Blue => TOP_PAGODA_INDEX,
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Piece {
     RedStudent,
     BlueStudent,
     RedMaster,
     BlueMaster,
+    /// From Way of the Wind. Either side can move it, and it doesn't belong to
+    /// either of them.
+    WindSpirit,
 }
 use Piece::*;
 
 impl Piece {
-    pub fn is_player(&self) -> bool {
-        match *self {
-            BlueStudent | BlueMaster => true,
-            _ => false,
+    /// `None` for the Wind Spirit.
+    pub fn colour(self) -> Option<PieceColour> {
+        match self {
+            BlueStudent | BlueMaster => Some(Blue),
+            RedStudent | RedMaster => Some(Red),
+            WindSpirit => None,
         }
     }
-    pub fn colour(self) -> PieceColour {
+
+    /// Used when writing positions as text. Masters are upper case.
+    pub fn letter(self) -> char {
         match self {
-            BlueStudent | BlueMaster => Blue,
-            RedStudent | RedMaster => Red,
+            RedStudent => 'r',
+            BlueStudent => 'b',
+            RedMaster => 'R',
+            BlueMaster => 'B',
+            WindSpirit => 'w',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<Piece> {
+        match letter {
+            'r' => Some(RedStudent),
+            'b' => Some(BlueStudent),
+            'R' => Some(RedMaster),
+            'B' => Some(BlueMaster),
+            'w' => Some(WindSpirit),
+            _ => None,
         }
     }
 }
@@ -342,6 +919,16 @@ pub enum PieceColour {
 }
 use PieceColour::*;
 
+impl PieceColour {
+    /// Accepts the names `Display` gives in any case.
+    pub fn from_name(name: &str) -> Option<PieceColour> {
+        [Red, Blue]
+            .iter()
+            .find(|colour| colour.to_string().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+}
+
 impl fmt::Display for PieceColour {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -355,6 +942,696 @@ impl fmt::Display for PieceColour {
     }
 }
 
+impl PieceColour {
+    pub fn opponent(self) -> PieceColour {
+        match self {
+            Red => Blue,
+            Blue => Red,
+        }
+    }
+}
+
+/// Everything needed to play the game by the rules, with none of the UI state.
+/// Bots, tests and tools can use this directly without a `Platform`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GameState {
+    pub board: Board,
+    pub blue_cards: (Card, Card),
+    pub red_cards: (Card, Card),
+    pub center_card: Card,
+    pub to_move: PieceColour,
+}
+
+/// What a player does on their turn: move a piece using one of their cards, or,
+/// if they have no moves at all, give up one of their cards without moving.
+/// With a wind card they move one of their pieces and then the Wind Spirit.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Ply {
+    Move(Move, PairIndex),
+    Wind(WindMove, PairIndex),
+    Pass(PairIndex),
+}
+
+/// The two parts of using a wind card, which happen in this order.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WindMove {
+    pub piece: Move,
+    pub spirit: Move,
+}
+
+impl GameState {
+    /// Sets up the starting board and deals five cards from a shuffled base
+    /// game deck. The stamp on the center card decides who moves first.
+    pub fn deal<R: Rng>(rng: &mut R) -> Self {
+        GameState::deal_from(rng, Deck::Base)
+    }
+
+    pub fn deal_from<R: Rng>(rng: &mut R, deck_kind: Deck) -> Self {
+        let mut deck = deck_kind.cards();
+        rng.shuffle(&mut deck);
+
+        debug_assert!(deck.len() >= 5);
+
+        let blue_cards = (deck.pop().unwrap(), deck.pop().unwrap());
+        let red_cards = (deck.pop().unwrap(), deck.pop().unwrap());
+
+        let center_card = deck.pop().unwrap();
+
+        let mut board = starting_board();
+        if deck_kind.has_wind_spirit() {
+            board[CENTER_INDEX] = Some(WindSpirit);
+        }
+
+        GameState {
+            board,
+            blue_cards,
+            red_cards,
+            center_card,
+            to_move: center_card.stamp(),
+        }
+    }
+
+    pub fn cards(&self, colour: PieceColour) -> (Card, Card) {
+        match colour {
+            Red => self.red_cards,
+            Blue => self.blue_cards,
+        }
+    }
+
+    fn cards_mut(&mut self, colour: PieceColour) -> &mut (Card, Card) {
+        match colour {
+            Red => &mut self.red_cards,
+            Blue => &mut self.blue_cards,
+        }
+    }
+
+    pub fn card(&self, colour: PieceColour, pair_index: PairIndex) -> Card {
+        get_card(&self.cards(colour), pair_index)
+    }
+
+    /// All the single moves the side to move could make, including moving the
+    /// Wind Spirit, ignoring whether the game is already over. Wind cards
+    /// don't make single moves, see `wind_moves`.
+    pub fn legal_moves(&self) -> Vec<(Move, PairIndex)> {
+        let colour = self.to_move;
+        let cards = self.cards(colour);
+
+        let mut result: Vec<(Move, PairIndex)> = get_moves(&self.board, &cards, colour)
+            .into_iter()
+            .filter(|&(_, pair_index)| !get_card(&cards, pair_index).is_wind())
+            .collect();
+
+        if let Some(spirit_index) = get_spirit_index(&self.board) {
+            for &(pair_index, card) in [(First, cards.0), (Second, cards.1)].iter() {
+                if card.is_wind() {
+                    continue;
+                }
+
+                for m in spirit_moves(&self.board, card.offsets(), spirit_index, colour) {
+                    result.push((m, pair_index));
+                }
+            }
+        }
+
+        result
+    }
+
+    /// All the ways the side to move could use their wind cards, if they have
+    /// any and the Wind Spirit is on the board.
+    pub fn wind_moves(&self) -> Vec<(WindMove, PairIndex)> {
+        let colour = self.to_move;
+        let cards = self.cards(colour);
+        let mut result = Vec::new();
+
+        let spirit_index = match get_spirit_index(&self.board) {
+            Some(spirit_index) => spirit_index,
+            None => return result,
+        };
+
+        for &(pair_index, card) in [(First, cards.0), (Second, cards.1)].iter() {
+            if !card.is_wind() {
+                continue;
+            }
+
+            for source_index in get_piece_indices(&self.board, colour) {
+                for piece in valid_moves(&self.board, &card, source_index, colour) {
+                    let board = apply_move(&self.board, piece);
+
+                    let spirits = spirit_moves(&board, card.spirit_offsets(), spirit_index, colour);
+
+                    for spirit in spirits {
+                        result.push((WindMove { piece, spirit }, pair_index));
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    /// True if the side to move has no moves and so has to give up a card
+    /// without moving a piece.
+    pub fn must_pass(&self) -> bool {
+        self.outcome().is_none() && self.legal_moves().is_empty() && self.wind_moves().is_empty()
+    }
+
+    /// `legal_moves` and `wind_moves` together, or passing when there are
+    /// none of either. Empty once the game is over.
+    pub fn legal_plies(&self) -> Vec<Ply> {
+        if self.outcome().is_some() {
+            return Vec::new();
+        }
+
+        let mut plies: Vec<Ply> = self.legal_moves()
+            .into_iter()
+            .map(|(m, pair_index)| Ply::Move(m, pair_index))
+            .collect();
+
+        plies.extend(
+            self.wind_moves()
+                .into_iter()
+                .map(|(wind_move, pair_index)| Ply::Wind(wind_move, pair_index)),
+        );
+
+        if plies.is_empty() {
+            vec![Ply::Pass(First), Ply::Pass(Second)]
+        } else {
+            plies
+        }
+    }
+
+    /// Makes the ply for the side to move, then passes the turn to the other side.
+    /// This does not check that the ply is legal.
+    pub fn apply(&mut self, ply: Ply) {
+        let pair_index = match ply {
+            Ply::Move(m, pair_index) => {
+                self.board = apply_move(&self.board, m);
+                pair_index
+            }
+            Ply::Wind(wind_move, pair_index) => {
+                let board = apply_move(&self.board, wind_move.piece);
+                self.board = apply_move(&board, wind_move.spirit);
+                pair_index
+            }
+            Ply::Pass(pair_index) => pair_index,
+        };
+
+        let colour = self.to_move;
+        let mut center_card = self.center_card;
+        swap_cards(&mut center_card, self.cards_mut(colour), pair_index);
+        self.center_card = center_card;
+
+        self.to_move = colour.opponent();
+    }
+
+    /// Returns the winning colour if the game is over.
+    pub fn outcome(&self) -> Option<PieceColour> {
+        winner(&self.board)
+    }
+
+    /// Checks that this position could come up in a game, including one that
+    /// is over: each side has at most one master and up to four students, at
+    /// least one master is left, there is at most one Wind Spirit and the five
+    /// cards are all different.
+    pub fn check_position(&self) -> Result<(), String> {
+        for &colour in [Blue, Red].iter() {
+            let masters = self.master_count(colour);
+
+            if masters > 1 {
+                return Err(format!("{} can have at most one master, not {}", colour, masters));
+            }
+
+            let students = piece_count_of_colour(&self.board, colour) - masters;
+
+            if students > 4 {
+                return Err(format!(
+                    "{} can have at most four students, not {}",
+                    colour,
+                    students
+                ));
+            }
+        }
+
+        if self.master_count(Blue) + self.master_count(Red) == 0 {
+            return Err("at least one master has to be on the board".to_string());
+        }
+
+        let spirits = self.board.iter().filter(|&&square| square == Some(WindSpirit)).count();
+
+        if spirits > 1 {
+            return Err(format!("there can be at most one Wind Spirit, not {}", spirits));
+        }
+
+        let cards = [
+            self.blue_cards.0,
+            self.blue_cards.1,
+            self.red_cards.0,
+            self.red_cards.1,
+            self.center_card,
+        ];
+
+        for (i, card) in cards.iter().enumerate() {
+            if cards[i + 1..].contains(card) {
+                return Err(format!("there is more than one {} card", card));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Like `check_position`, but also checks that a game could start from
+    /// here: each side has its master and nobody has won yet.
+    pub fn check_setup(&self) -> Result<(), String> {
+        for &colour in [Blue, Red].iter() {
+            let masters = self.master_count(colour);
+
+            if masters != 1 {
+                return Err(format!("{} needs exactly one master, not {}", colour, masters));
+            }
+        }
+
+        self.check_position()?;
+
+        if let Some(winner) = self.outcome() {
+            return Err(format!("{} has already won", winner));
+        }
+
+        Ok(())
+    }
+
+    fn master_count(&self, colour: PieceColour) -> usize {
+        let master = match colour {
+            Blue => BlueMaster,
+            Red => RedMaster,
+        };
+
+        self.board.iter().filter(|&&square| square == Some(master)).count()
+    }
+}
+
+pub fn starting_board() -> Board {
+    let mut board = [None; 25];
+    board[0] = Some(RedStudent);
+    board[1] = Some(RedStudent);
+    board[TOP_PAGODA_INDEX] = Some(RedMaster);
+    board[3] = Some(RedStudent);
+    board[4] = Some(RedStudent);
+
+    board[20] = Some(BlueStudent);
+    board[21] = Some(BlueStudent);
+    board[BOTTOM_PAGODA_INDEX] = Some(BlueMaster);
+    board[23] = Some(BlueStudent);
+    board[24] = Some(BlueStudent);
+
+    board
+}
+
+pub fn get_card(cards: &(Card, Card), pair_index: PairIndex) -> Card {
+    match pair_index {
+        First => cards.0,
+        Second => cards.1,
+    }
+}
+
+pub fn get_moves(board: &Board, cards: &(Card, Card), colour: PieceColour) -> Vec<(Move, PairIndex)> {
+    let pieces = get_piece_indices(board, colour);
+
+    let mut result = Vec::new();
+
+    for piece in pieces.iter() {
+        result.extend(valid_moves(board, &cards.0, *piece, colour).iter().map(
+            |m| {
+                (*m, First)
+            },
+        ));
+        result.extend(valid_moves(board, &cards.1, *piece, colour).iter().map(
+            |m| {
+                (*m, Second)
+            },
+        ));
+    }
+
+    result
+}
+
+pub fn get_piece_indices(board: &Board, colour: PieceColour) -> Vec<usize> {
+    board
+        .iter()
+        .enumerate()
+        .filter_map(|(index, piece)| {
+            piece.and_then(|p| if p.colour() == Some(colour) {
+                Some(index)
+            } else {
+                None
+            })
+
+        })
+        .collect()
+}
+
+pub fn piece_count_of_colour(board: &Board, piece_colour: PieceColour) -> usize {
+    board
+        .iter()
+        .filter(|p| {
+            p.map(|piece| piece.colour() == Some(piece_colour)).unwrap_or(
+                false,
+            )
+        })
+        .count()
+}
+
+/// The random number generator a game with this seed uses, both to deal the
+/// cards and afterwards, so the same seed always gives the same game.
+pub fn seeded_rng(seed: usize) -> StdRng {
+    let seed: &[_] = &[seed];
+    SeedableRng::from_seed(seed)
+}
+
+pub fn winner(board: &Board) -> Option<PieceColour> {
+    if blue_wins(board) {
+        Some(Blue)
+    } else if red_wins(board) {
+        Some(Red)
+    } else {
+        None
+    }
+}
+
+pub fn blue_wins(board: &Board) -> bool {
+    if let Some(master_index) = get_master_index(board, Blue) {
+        master_index == TOP_PAGODA_INDEX || get_master_index(board, Red).is_none()
+    } else {
+        false
+    }
+}
+pub fn red_wins(board: &Board) -> bool {
+    if let Some(master_index) = get_master_index(board, Red) {
+        master_index == BOTTOM_PAGODA_INDEX || get_master_index(board, Blue).is_none()
+    } else {
+        false
+    }
+}
+
+pub fn get_master_index(board: &Board, colour: PieceColour) -> Option<usize> {
+    let master = match colour {
+        Red => RedMaster,
+        Blue => BlueMaster,
+    };
+
+    board.iter().position(|p| *p == Some(master))
+}
+
+/// Anything on the target square is captured, except that the Wind Spirit
+/// swaps places with whatever it moves onto instead.
+pub fn apply_move(board: &Board, current_move: Move) -> Board {
+    let mut result = *board;
+
+    let piece = result[current_move.source_index];
+    let target = result[current_move.target_index];
+    result[current_move.target_index] = piece;
+    result[current_move.source_index] = if piece == Some(WindSpirit) {
+        target
+    } else {
+        None
+    };
+
+    result
+}
+
+pub fn swap_cards(center_card: &mut Card, cards: &mut (Card, Card), pair_index: PairIndex) {
+    let swapped = get_swap_cards_result(*center_card, *cards, pair_index);
+
+    *center_card = swapped.center_card;
+    *cards = swapped.pair;
+}
+fn get_swap_cards_result(
+    center_card: Card,
+    cards: (Card, Card),
+    pair_index: PairIndex,
+) -> SwapCards {
+    match pair_index {
+        First => {
+            SwapCards {
+                center_card: cards.0,
+                pair: (center_card, cards.1),
+            }
+        }
+        Second => {
+            SwapCards {
+                center_card: cards.1,
+                pair: (cards.0, center_card),
+            }
+        }
+    }
+}
+
+struct SwapCards {
+    center_card: Card,
+    pair: (Card, Card),
+}
+
+#[cfg(test)]
+mod game_state {
+    use ::*;
+
+    fn dealt(seed: usize) -> GameState {
+        GameState::deal(&mut seeded_rng(seed))
+    }
+
+    quickcheck! {
+        fn apply_passes_the_turn_and_keeps_five_cards(seed: usize) -> bool {
+            let mut game = dealt(seed);
+            let before = game;
+
+            let ply = game.legal_plies()[0];
+            game.apply(ply);
+
+            let mut cards_before = vec![
+                before.blue_cards.0,
+                before.blue_cards.1,
+                before.red_cards.0,
+                before.red_cards.1,
+                before.center_card,
+            ];
+            let cards_after = [
+                game.blue_cards.0,
+                game.blue_cards.1,
+                game.red_cards.0,
+                game.red_cards.1,
+                game.center_card,
+            ];
+
+            cards_before.retain(|c| !cards_after.contains(c));
+
+            let other = before.to_move.opponent();
+
+            game.to_move == other && game.cards(other) == before.cards(other) &&
+                cards_before.is_empty()
+        }
+
+        fn the_center_card_stamp_moves_first(seed: usize) -> bool {
+            let game = dealt(seed);
+
+            game.to_move == game.center_card.stamp()
+        }
+    }
+
+    #[test]
+    fn half_the_cards_have_each_stamp() {
+        for &deck in [Deck::Base, Deck::SenseisPath].iter() {
+            let cards = deck.cards();
+            let red = cards.iter().filter(|card| card.stamp() == Red).count();
+
+            assert_eq!(red, cards.len() / 2);
+        }
+    }
+
+    #[test]
+    fn expansion_cards_come_in_mirrored_pairs() {
+        let mirrored = |card: Card| {
+            let mut offsets: Vec<_> = card.offsets().iter().map(|&(x, y)| (-x, y)).collect();
+            offsets.sort();
+            offsets
+        };
+
+        for &(card, mirror) in [
+            (Fox, Dog),
+            (Panda, Bear),
+            (SeaSnake, Viper),
+            (Mouse, Rat),
+            (Tanuki, Iguana),
+            (Sable, Otter),
+        ].iter()
+        {
+            let mut offsets = mirror.offsets().to_vec();
+            offsets.sort();
+
+            assert_eq!(mirrored(card), offsets);
+            assert_ne!(card.stamp(), mirror.stamp());
+        }
+    }
+
+    quickcheck! {
+        fn every_deck_deals_its_own_cards(seed: usize) -> bool {
+            let seed: &[_] = &[seed];
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+            Deck::all_values().into_iter().all(|deck| {
+                let game = GameState::deal_from(&mut rng, deck);
+                let cards = deck.cards();
+
+                [
+                    game.blue_cards.0,
+                    game.blue_cards.1,
+                    game.red_cards.0,
+                    game.red_cards.1,
+                    game.center_card,
+                ].iter()
+                    .all(|card| cards.contains(card))
+            })
+        }
+    }
+
+    #[test]
+    fn moving_onto_the_pagoda_wins() {
+        let mut game = dealt(42);
+        game.board = [None; 25];
+        game.board[7] = Some(BlueMaster);
+        game.board[20] = Some(RedMaster);
+        game.to_move = Blue;
+
+        game.apply(Ply::Move(
+            Move {
+                source_index: 7,
+                target_index: TOP_PAGODA_INDEX,
+            },
+            First,
+        ));
+
+        assert_eq!(game.outcome(), Some(Blue));
+        assert!(game.legal_plies().is_empty());
+    }
+
+    #[test]
+    fn passing_is_only_legal_without_moves() {
+        let mut game = dealt(42);
+
+        assert!(game.legal_plies().iter().all(|ply| match *ply {
+            Ply::Move(_, _) | Ply::Wind(_, _) => true,
+            Ply::Pass(_) => false,
+        }));
+
+        //a single column of blue pieces with Tiger only moves up and down that column
+        game.to_move = Blue;
+        game.board = [None; 25];
+        game.board[0] = Some(RedMaster);
+        for &i in [4, 9, 14, 19].iter() {
+            game.board[i] = Some(BlueStudent);
+        }
+        game.board[24] = Some(BlueMaster);
+        game.blue_cards = (Tiger, Tiger);
+
+        assert_eq!(game.legal_plies(), vec![Ply::Pass(First), Ply::Pass(Second)]);
+    }
+
+    #[test]
+    fn the_cpu_plays_whichever_side_the_player_does_not() {
+        let seed: &[_] = &[42];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+        for side in Side::all_values() {
+            let player = side.colour(&mut rng);
+            let mode = Mode::VsCpu(player);
+
+            assert!(!mode.is_cpu(player));
+            assert!(mode.is_cpu(player.opponent()));
+            assert_eq!(side.to_string().to_lowercase().parse(), Ok(side));
+        }
+
+        assert!(!Mode::HotSeat.is_cpu(Red));
+        assert!(Mode::Spectate.is_cpu(Blue) && Mode::Spectate.is_cpu(Red));
+    }
+
+    fn wind_game() -> GameState {
+        let seed: &[_] = &[42];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+        let mut game = GameState::deal_from(&mut rng, Deck::WayOfTheWind);
+        game.to_move = Blue;
+
+        game
+    }
+
+    #[test]
+    fn the_wind_spirit_swaps_with_students_but_not_masters() {
+        let mut game = wind_game();
+        assert_eq!(game.board[CENTER_INDEX], Some(WindSpirit));
+
+        //Tiger sends the spirit from c3 to c1, where the red master is, or c4
+        game.blue_cards = (Tiger, Crab);
+        let spirit_targets: Vec<usize> = game.legal_moves()
+            .into_iter()
+            .filter(|&(m, pair_index)| m.source_index == CENTER_INDEX && pair_index == First)
+            .map(|(m, _)| m.target_index)
+            .collect();
+        assert_eq!(spirit_targets, vec![17]);
+
+        game.board[17] = Some(RedStudent);
+        game.apply(Ply::Move(
+            Move {
+                source_index: CENTER_INDEX,
+                target_index: 17,
+            },
+            First,
+        ));
+
+        assert_eq!(game.board[17], Some(WindSpirit));
+        assert_eq!(game.board[CENTER_INDEX], Some(RedStudent));
+
+        //and no piece can take the spirit
+        assert!(!can_move_onto(Some(WindSpirit), Red));
+        assert!(!can_move_onto(Some(WindSpirit), Blue));
+    }
+
+    #[test]
+    fn wind_cards_move_a_piece_then_the_spirit() {
+        let mut game = wind_game();
+        game.blue_cards = (Gust, Crab);
+
+        let plies = game.legal_plies();
+        let ply = Ply::Wind(
+            WindMove {
+                piece: Move {
+                    source_index: 20,
+                    target_index: 16,
+                },
+                spirit: Move {
+                    source_index: CENTER_INDEX,
+                    target_index: 7,
+                },
+            },
+            First,
+        );
+
+        assert!(plies.contains(&ply));
+        //Gust can't be used to move just a piece
+        assert!(!plies.contains(&Ply::Move(
+            Move {
+                source_index: 20,
+                target_index: 16,
+            },
+            First,
+        )));
+
+        game.apply(ply);
+
+        assert_eq!(game.board[16], Some(BlueStudent));
+        assert_eq!(game.board[7], Some(WindSpirit));
+        assert_eq!(game.board[CENTER_INDEX], None);
+        assert_eq!(game.center_card, Gust);
+    }
+}
+
 pub type UiId = i32;
 
 pub struct UIContext {
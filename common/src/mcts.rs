@@ -0,0 +1,254 @@
+//! Monte Carlo tree search using UCT to pick which branch to explore next.
+
+use std::time::{Duration, Instant};
+
+use rand::{Rng, StdRng};
+
+use ::*;
+use bitboard::Position;
+use cpu::Agent;
+use search::{priority, Priority};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Rollout {
+    Random,
+    /// Take wins when they are available and avoid handing the opponent
+    /// immediate wins, otherwise play randomly.
+    Heuristic,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mcts {
+    pub iterations: u32,
+    /// With no time budget, the chosen ply only depends on the rng passed in.
+    pub time_budget: Option<Duration>,
+    pub exploration: f64,
+    pub rollout: Rollout,
+    /// Rollouts that go on longer than this count as a draw.
+    pub max_rollout_plies: u32,
+}
+
+impl Default for Mcts {
+    fn default() -> Self {
+        Mcts {
+            iterations: 2000,
+            time_budget: None,
+            exploration: std::f64::consts::SQRT_2,
+            rollout: Rollout::Heuristic,
+            max_rollout_plies: 200,
+        }
+    }
+}
+
+struct Node {
+    position: Position,
+    parent: Option<usize>,
+    ply: Option<Ply>,
+    children: Vec<usize>,
+    untried: Vec<Ply>,
+    visits: u32,
+    /// From the point of view of the player who made `ply`.
+    wins: f64,
+}
+
+impl Node {
+    fn new(position: Position, parent: Option<usize>, ply: Option<Ply>) -> Self {
+        Node {
+            position,
+            parent,
+            ply,
+            children: Vec::new(),
+            untried: position.legal_plies().to_vec(),
+            visits: 0,
+            wins: 0.0,
+        }
+    }
+}
+
+impl Mcts {
+    /// Returns `None` if the game is already over.
+    pub fn search<R: Rng>(&self, game: &GameState, rng: &mut R) -> Option<Ply> {
+        let root = Node::new(Position::from(game), None, None);
+
+        if root.untried.is_empty() {
+            return None;
+        }
+
+        let mut tree = vec![root];
+
+        let deadline = self.time_budget.map(|budget| Instant::now() + budget);
+
+        for _ in 0..self.iterations {
+            if deadline.map(|d| Instant::now() >= d).unwrap_or(false) {
+                break;
+            }
+
+            let mut index = self.select(&tree);
+
+            if !tree[index].untried.is_empty() {
+                index = expand(&mut tree, index, rng);
+            }
+
+            let winner = self.rollout(tree[index].position, rng);
+
+            backpropagate(&mut tree, index, winner);
+        }
+
+        tree[0]
+            .children
+            .iter()
+            .max_by_key(|&&child| tree[child].visits)
+            .and_then(|&child| tree[child].ply)
+            .or_else(|| tree[0].untried.first().cloned())
+    }
+
+    fn select(&self, tree: &[Node]) -> usize {
+        let mut index = 0;
+
+        while tree[index].untried.is_empty() && !tree[index].children.is_empty() {
+            let parent_visits = tree[index].visits as f64;
+
+            index = *tree[index]
+                .children
+                .iter()
+                .max_by(|&&a, &&b| {
+                    self.uct(&tree[a], parent_visits)
+                        .partial_cmp(&self.uct(&tree[b], parent_visits))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .unwrap();
+        }
+
+        index
+    }
+
+    fn uct(&self, node: &Node, parent_visits: f64) -> f64 {
+        let visits = node.visits as f64;
+
+        node.wins / visits + self.exploration * (parent_visits.ln() / visits).sqrt()
+    }
+
+    fn rollout<R: Rng>(&self, mut position: Position, rng: &mut R) -> Option<PieceColour> {
+        for _ in 0..self.max_rollout_plies {
+            if let Some(winner) = position.outcome() {
+                return Some(winner);
+            }
+
+            let plies = position.legal_plies();
+
+            let ply = match self.rollout {
+                Rollout::Random => *rng.choose(&plies).unwrap(),
+                Rollout::Heuristic => heuristic_rollout_ply(&position, &plies, rng),
+            };
+
+            position.apply(ply);
+        }
+
+        position.outcome()
+    }
+}
+
+fn heuristic_rollout_ply<R: Rng>(position: &Position, plies: &[Ply], rng: &mut R) -> Ply {
+    let mut not_losing = Vec::with_capacity(plies.len());
+
+    for &ply in plies.iter() {
+        match priority(position, ply) {
+            Priority::Winning => return ply,
+            Priority::Losing => {}
+            _ => not_losing.push(ply),
+        }
+    }
+
+    *rng.choose(&not_losing)
+        .or_else(|| rng.choose(plies))
+        .unwrap()
+}
+
+fn expand<R: Rng>(tree: &mut Vec<Node>, index: usize, rng: &mut R) -> usize {
+    let untried_index = rng.gen_range(0, tree[index].untried.len());
+    let ply = tree[index].untried.swap_remove(untried_index);
+
+    let mut position = tree[index].position;
+    position.apply(ply);
+
+    let child = tree.len();
+    tree.push(Node::new(position, Some(index), Some(ply)));
+    tree[index].children.push(child);
+
+    child
+}
+
+fn backpropagate(tree: &mut [Node], mut index: usize, winner: Option<PieceColour>) {
+    loop {
+        let node = &mut tree[index];
+
+        node.visits += 1;
+
+        let mover = node.position.to_move.opponent();
+        node.wins += match winner {
+            Some(colour) if colour == mover => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        };
+
+        match node.parent {
+            Some(parent) => index = parent,
+            None => break,
+        }
+    }
+}
+
+impl Agent for Mcts {
+    fn choose_ply(&mut self, game: &GameState, rng: &mut StdRng) -> Option<Ply> {
+        self.search(game, rng)
+    }
+}
+
+#[cfg(test)]
+mod tree_search {
+    use fixtures::{lone_masters, rng};
+    use mcts::*;
+
+    #[test]
+    fn takes_an_immediate_win() {
+        for &rollout in [Rollout::Random, Rollout::Heuristic].iter() {
+            let mcts = Mcts {
+                iterations: 500,
+                rollout,
+                ..Mcts::default()
+            };
+
+            let game = lone_masters(7, 20);
+
+            let mut next = game;
+            next.apply(mcts.search(&game, &mut rng()).unwrap());
+
+            assert_eq!(next.outcome(), Some(Blue));
+        }
+    }
+
+    #[test]
+    fn is_reproducible_without_a_time_budget() {
+        let game = GameState::deal(&mut rng());
+        let mcts = Mcts {
+            iterations: 300,
+            ..Mcts::default()
+        };
+
+        assert_eq!(mcts.search(&game, &mut rng()), mcts.search(&game, &mut rng()));
+    }
+
+    #[test]
+    fn passes_when_it_cannot_move() {
+        let mut game = lone_masters(24, 0);
+        for &i in [4, 9, 14, 19].iter() {
+            game.board[i] = Some(BlueStudent);
+        }
+        game.blue_cards = (Tiger, Tiger);
+
+        match Mcts::default().search(&game, &mut rng()) {
+            Some(Ply::Pass(_)) => {}
+            other => panic!("{:?} is not a pass", other),
+        }
+    }
+}
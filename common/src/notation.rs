@@ -0,0 +1,525 @@
+//! A short text form for plies, like "Tiger c5-c3x" or "Frog pass".
+//!
+//! Squares are named with a file from a to e, left to right, and a rank from 1
+//! to 5, top to bottom as the board is drawn, so Red starts on rank 1 and Blue
+//! on rank 5. A trailing "x" marks a capture. Wind cards move a piece and then
+//! the Wind Spirit, written together like "Gust b5-a4x/c3-c2".
+
+use std::fmt;
+use std::str::FromStr;
+
+use ::*;
+use history::Record;
+
+/// A ply written down on its own. Which card was used is given by name, so
+/// `to_ply` needs to know whose hand it was in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Notation {
+    Move {
+        card: Card,
+        source_index: usize,
+        target_index: usize,
+        capture: bool,
+    },
+    Wind {
+        card: Card,
+        source_index: usize,
+        target_index: usize,
+        capture: bool,
+        spirit_source_index: usize,
+        spirit_target_index: usize,
+    },
+    Pass { card: Card },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotationError {
+    Empty,
+    BadCard(UnknownCard),
+    /// There was a card name but nothing saying what was done with it.
+    MissingMove(String),
+    BadSquare(String),
+    BadMove(String),
+    /// The card is not in the hand of the side to move.
+    NotInHand(Card, PieceColour),
+    /// The notation's "x" disagrees with whether the target square is occupied.
+    WrongCapture(String),
+    Illegal(String),
+}
+use self::NotationError::*;
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Empty => write!(f, "expected a card name and a move, found nothing"),
+            BadCard(ref e) => write!(f, "{}", e),
+            MissingMove(ref card) => {
+                write!(
+                    f,
+                    "expected a move like \"c5-c4\" or \"pass\" after \"{}\"",
+                    card
+                )
+            }
+            BadSquare(ref s) => {
+                write!(
+                    f,
+                    "\"{}\" is not a square, squares go from a1 to e5",
+                    s
+                )
+            }
+            BadMove(ref s) => {
+                write!(
+                    f,
+                    concat!(
+                        "\"{}\" is not a move, ",
+                        "expected something like \"c5-c4\", \"c5-c4/c3-c2\" or \"pass\""
+                    ),
+                    s
+                )
+            }
+            NotInHand(card, colour) => write!(f, "{} does not have the {} card", colour, card),
+            WrongCapture(ref s) => write!(f, "{}", s),
+            Illegal(ref s) => write!(f, "{} is not a legal ply here", s),
+        }
+    }
+}
+
+impl From<UnknownCard> for NotationError {
+    fn from(e: UnknownCard) -> Self {
+        BadCard(e)
+    }
+}
+
+pub fn square_name(index: usize) -> String {
+    match get_board_xy(index) {
+        Some((x, y)) => format!("{}{}", (b'a' + x as u8) as char, y + 1),
+        None => "??".to_string(),
+    }
+}
+
+pub fn parse_square(s: &str) -> Result<usize, NotationError> {
+    let bad_square = || BadSquare(s.to_string());
+
+    let mut chars = s.chars();
+
+    let (file, rank) = match (chars.next(), chars.next(), chars.next()) {
+        (Some(file), Some(rank), None) => (file.to_ascii_lowercase(), rank),
+        _ => return Err(bad_square()),
+    };
+
+    if !('a'..='e').contains(&file) || !('1'..='5').contains(&rank) {
+        return Err(bad_square());
+    }
+
+    let x = file as usize - 'a' as usize;
+    let y = rank as usize - '1' as usize;
+
+    get_board_index(x, y).ok_or_else(bad_square)
+}
+
+//The Wind Spirit swaps places with whatever it moves onto rather than
+//capturing it.
+fn is_capture(game: &GameState, m: Move) -> bool {
+    game.board[m.source_index] != Some(WindSpirit) && game.board[m.target_index].is_some()
+}
+
+fn capture_error(notation: &Notation, target_index: usize, capture: bool) -> NotationError {
+    if capture {
+        WrongCapture(format!(
+            "{} is marked as a capture but nothing is captured on {}",
+            notation,
+            square_name(target_index)
+        ))
+    } else {
+        WrongCapture(format!(
+            "{} captures on {} but is missing the \"x\"",
+            notation,
+            square_name(target_index)
+        ))
+    }
+}
+
+impl Notation {
+    /// Writes down `ply` as played by the side to move in `game`.
+    pub fn new(game: &GameState, ply: Ply) -> Self {
+        match ply {
+            Ply::Move(m, pair_index) => {
+                Notation::Move {
+                    card: game.card(game.to_move, pair_index),
+                    source_index: m.source_index,
+                    target_index: m.target_index,
+                    capture: is_capture(game, m),
+                }
+            }
+            Ply::Wind(wind_move, pair_index) => {
+                Notation::Wind {
+                    card: game.card(game.to_move, pair_index),
+                    source_index: wind_move.piece.source_index,
+                    target_index: wind_move.piece.target_index,
+                    capture: is_capture(game, wind_move.piece),
+                    spirit_source_index: wind_move.spirit.source_index,
+                    spirit_target_index: wind_move.spirit.target_index,
+                }
+            }
+            Ply::Pass(pair_index) => Notation::Pass { card: game.card(game.to_move, pair_index) },
+        }
+    }
+
+    pub fn card(&self) -> Card {
+        match *self {
+            Notation::Move { card, .. } |
+            Notation::Wind { card, .. } |
+            Notation::Pass { card } => card,
+        }
+    }
+
+    /// Works out which ply this is for the side to move in `game`, checking
+    /// that it is legal.
+    pub fn to_ply(&self, game: &GameState) -> Result<Ply, NotationError> {
+        let colour = game.to_move;
+        let cards = game.cards(colour);
+
+        let pair_index = if cards.0 == self.card() {
+            PairIndex::First
+        } else if cards.1 == self.card() {
+            PairIndex::Second
+        } else {
+            return Err(NotInHand(self.card(), colour));
+        };
+
+        let ply = match *self {
+            Notation::Move {
+                source_index,
+                target_index,
+                capture,
+                ..
+            } => {
+                let m = Move {
+                    source_index,
+                    target_index,
+                };
+
+                if capture != is_capture(game, m) {
+                    return Err(capture_error(self, target_index, capture));
+                }
+
+                Ply::Move(m, pair_index)
+            }
+            Notation::Wind {
+                source_index,
+                target_index,
+                capture,
+                spirit_source_index,
+                spirit_target_index,
+                ..
+            } => {
+                let piece = Move {
+                    source_index,
+                    target_index,
+                };
+
+                if capture != is_capture(game, piece) {
+                    return Err(capture_error(self, target_index, capture));
+                }
+
+                Ply::Wind(
+                    WindMove {
+                        piece,
+                        spirit: Move {
+                            source_index: spirit_source_index,
+                            target_index: spirit_target_index,
+                        },
+                    },
+                    pair_index,
+                )
+            }
+            Notation::Pass { .. } => Ply::Pass(pair_index),
+        };
+
+        if game.legal_plies().contains(&ply) {
+            Ok(ply)
+        } else {
+            Err(Illegal(self.to_string()))
+        }
+    }
+}
+
+impl<'a> From<&'a Record> for Notation {
+    fn from(record: &'a Record) -> Self {
+        match record.ply {
+            Ply::Move(m, _) => {
+                Notation::Move {
+                    card: record.card,
+                    source_index: m.source_index,
+                    target_index: m.target_index,
+                    capture: record.captured.is_some(),
+                }
+            }
+            Ply::Wind(wind_move, _) => {
+                Notation::Wind {
+                    card: record.card,
+                    source_index: wind_move.piece.source_index,
+                    target_index: wind_move.piece.target_index,
+                    capture: record.captured.is_some(),
+                    spirit_source_index: wind_move.spirit.source_index,
+                    spirit_target_index: wind_move.spirit.target_index,
+                }
+            }
+            Ply::Pass(_) => Notation::Pass { card: record.card },
+        }
+    }
+}
+
+impl fmt::Display for Notation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Notation::Move {
+                card,
+                source_index,
+                target_index,
+                capture,
+            } => {
+                write!(
+                    f,
+                    "{} {}-{}{}",
+                    card,
+                    square_name(source_index),
+                    square_name(target_index),
+                    if capture { "x" } else { "" }
+                )
+            }
+            Notation::Wind {
+                card,
+                source_index,
+                target_index,
+                capture,
+                spirit_source_index,
+                spirit_target_index,
+            } => {
+                write!(
+                    f,
+                    "{} {}-{}{}/{}-{}",
+                    card,
+                    square_name(source_index),
+                    square_name(target_index),
+                    if capture { "x" } else { "" },
+                    square_name(spirit_source_index),
+                    square_name(spirit_target_index)
+                )
+            }
+            Notation::Pass { card } => write!(f, "{} pass", card),
+        }
+    }
+}
+
+impl FromStr for Notation {
+    type Err = NotationError;
+
+    /// The last word is the move and everything before it is the card name,
+    /// so card names may contain spaces.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+
+        let (last, rest) = match words.split_last() {
+            Some(split) => split,
+            None => return Err(Empty),
+        };
+
+        if rest.is_empty() {
+            return match last.parse::<Card>() {
+                Ok(_) => Err(MissingMove(last.to_string())),
+                Err(e) => Err(e.into()),
+            };
+        }
+
+        let card: Card = rest.join(" ").parse()?;
+
+        if last.eq_ignore_ascii_case("pass") {
+            return Ok(Notation::Pass { card });
+        }
+
+        let mut parts = last.split('/');
+
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(piece), None, None) => {
+                let (source_index, target_index, capture) = parse_squares(piece, last)?;
+
+                Ok(Notation::Move {
+                    card,
+                    source_index,
+                    target_index,
+                    capture,
+                })
+            }
+            (Some(piece), Some(spirit), None) => {
+                let (source_index, target_index, capture) = parse_squares(piece, last)?;
+                let (spirit_source_index, spirit_target_index, spirit_capture) =
+                    parse_squares(spirit, last)?;
+
+                if spirit_capture {
+                    return Err(WrongCapture(format!(
+                        "{} marks the Wind Spirit's move as a capture but it never captures",
+                        s.trim()
+                    )));
+                }
+
+                Ok(Notation::Wind {
+                    card,
+                    source_index,
+                    target_index,
+                    capture,
+                    spirit_source_index,
+                    spirit_target_index,
+                })
+            }
+            _ => Err(BadMove(last.to_string())),
+        }
+    }
+}
+
+//"c5-c3x" into its squares and whether it is marked as a capture. `word` is
+//the whole word it came from, for errors.
+fn parse_squares(squares: &str, word: &str) -> Result<(usize, usize, bool), NotationError> {
+    let (squares, capture) = match squares.strip_suffix(['x', 'X']) {
+        Some(squares) => (squares, true),
+        None => (squares, false),
+    };
+
+    let mut split = squares.split('-');
+
+    match (split.next(), split.next(), split.next()) {
+        (Some(source), Some(target), None) => {
+            Ok((parse_square(source)?, parse_square(target)?, capture))
+        }
+        _ => Err(BadMove(word.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod round_trip {
+    use notation::*;
+    use rand::{Rng, SeedableRng, StdRng};
+
+    quickcheck! {
+        fn squares_round_trip(index: usize) -> bool {
+            let index = index % 25;
+
+            parse_square(&square_name(index)) == Ok(index)
+        }
+
+        fn every_ply_of_a_game_round_trips(seed: usize) -> bool {
+            plies_round_trip(seed, Deck::Base)
+        }
+
+        fn every_ply_of_a_wind_game_round_trips(seed: usize) -> bool {
+            plies_round_trip(seed, Deck::WayOfTheWind)
+        }
+    }
+
+    fn plies_round_trip(seed: usize, deck: Deck) -> bool {
+        let seed: &[_] = &[seed];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let mut game = GameState::deal_from(&mut rng, deck);
+
+        for _ in 0..200 {
+            let ply = match rng.choose(&game.legal_plies()) {
+                Some(&ply) => ply,
+                None => break,
+            };
+
+            let notation = Notation::new(&game, ply);
+            let parsed = notation.to_string().parse::<Notation>();
+
+            if parsed != Ok(notation) || notation.to_ply(&game) != Ok(ply) {
+                return false;
+            }
+
+            game.apply(ply);
+        }
+
+        true
+    }
+
+    #[test]
+    fn formats_like_the_examples() {
+        let capture = Notation::Move {
+            card: Tiger,
+            source_index: 22,
+            target_index: 12,
+            capture: true,
+        };
+
+        assert_eq!(capture.to_string(), "Tiger c5-c3x");
+        assert_eq!(Notation::Pass { card: Frog }.to_string(), "Frog pass");
+
+        let wind = Notation::Wind {
+            card: Gust,
+            source_index: 21,
+            target_index: 15,
+            capture: true,
+            spirit_source_index: 12,
+            spirit_target_index: 17,
+        };
+
+        assert_eq!(wind.to_string(), "Gust b5-a4x/c3-c4");
+        assert!(matches!("Gust b5-a4/c3-c4x".parse::<Notation>(), Err(WrongCapture(_))));
+        assert_eq!("gust B5-A4X/c3-c4".parse(), Ok(wind));
+    }
+
+    #[test]
+    fn parsing_is_forgiving_about_case_and_spacing() {
+        assert_eq!(
+            "  tiger   C5-C3X ".parse(),
+            Ok(Notation::Move {
+                card: Tiger,
+                source_index: 22,
+                target_index: 12,
+                capture: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parsing_explains_what_went_wrong() {
+        let error = |s: &str| s.parse::<Notation>().unwrap_err();
+
+        assert_eq!(error(""), Empty);
+        assert_eq!(error("Tiger"), MissingMove("Tiger".to_string()));
+        assert_eq!(error("Tigger c5-c3"), BadCard(UnknownCard("Tigger".to_string())));
+        assert_eq!(error("Tiger c6-c3"), BadSquare("c6".to_string()));
+        assert_eq!(error("Tiger c5c3"), BadMove("c5c3".to_string()));
+    }
+
+    #[test]
+    fn to_ply_checks_against_the_game() {
+        let mut game = GameState {
+            board: starting_board(),
+            blue_cards: (Tiger, Crab),
+            red_cards: (Monkey, Frog),
+            center_card: Rabbit,
+            to_move: Blue,
+        };
+
+        let parse = |s: &str| s.parse::<Notation>().unwrap();
+
+        assert_eq!(parse("Frog c5-c4").to_ply(&game), Err(NotInHand(Frog, Blue)));
+        assert_eq!(
+            parse("Tiger c5-c3").to_ply(&game),
+            Ok(Ply::Move(
+                Move {
+                    source_index: 22,
+                    target_index: 12,
+                },
+                PairIndex::First,
+            ))
+        );
+        assert!(matches!(
+            parse("Tiger c5-c3x").to_ply(&game),
+            Err(WrongCapture(_))
+        ));
+        assert_eq!(parse("Tiger c5-c2").to_ply(&game), Err(Illegal("Tiger c5-c2".to_string())));
+
+        game.to_move = Red;
+        assert_eq!(parse("Tiger c1-c3").to_ply(&game), Err(NotInHand(Tiger, Red)));
+    }
+}
@@ -0,0 +1,62 @@
+//! Counting every sequence of plies up to a given depth, to check that move
+//! generation produces exactly the right moves.
+
+use ::*;
+use bitboard::Position;
+
+/// The number of distinct sequences of exactly `depth` plies from `game`. Games
+/// that end earlier than that do not count.
+pub fn perft(game: &GameState, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let plies = game.legal_plies();
+
+    if depth == 1 {
+        return plies.len() as u64;
+    }
+
+    plies
+        .iter()
+        .map(|&ply| {
+            let mut next = *game;
+            next.apply(ply);
+            perft(&next, depth - 1)
+        })
+        .sum()
+}
+
+/// `perft` for each ply available from `game`, in `legal_plies` order.
+pub fn divide(game: &GameState, depth: u32) -> Vec<(Ply, u64)> {
+    game.legal_plies()
+        .iter()
+        .map(|&ply| {
+            let mut next = *game;
+            next.apply(ply);
+            (ply, perft(&next, depth.saturating_sub(1)))
+        })
+        .collect()
+}
+
+/// The same as `perft` but uses the bitboard move generation.
+pub fn perft_position(position: &Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let plies = position.legal_plies();
+
+    if depth == 1 {
+        return plies.len() as u64;
+    }
+
+    plies
+        .iter()
+        .map(|&ply| {
+            let mut next = *position;
+            next.apply(ply);
+            perft_position(&next, depth - 1)
+        })
+        .sum()
+}
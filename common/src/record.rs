@@ -0,0 +1,516 @@
+//! Records of complete games for sharing and review, in a format modelled on
+//! chess's PGN: a few headers in square brackets followed by the plies in
+//! notation, numbered by turn.
+//!
+//! ```text
+//! [Date "2026.10.18"]
+//! [Blue "Player"]
+//! [Red "CPU (Strong Minimax)"]
+//! [Seed "42"]
+//! [Deal "Blue: Tiger, Crab; Red: Monkey, Frog; Center: Rabbit"]
+//! [Result "Blue"]
+//! [Reason "Way of the Stream"]
+//!
+//! 1. Tiger c5-c3 Monkey b1-c2 2. ...
+//! Blue
+//! ```
+//!
+//! The result at the end is "Blue", "Red" or "*" if the game is unfinished.
+//! Games that did not start from the usual starting position have a
+//! `[Position "..."]` header as well, in the form from the `fen` module.
+//! Importing replays every ply, so a record that breaks the rules anywhere
+//! will not load.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use ::*;
+use history::History;
+use notation::Notation;
+use save::SavedGame;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WinReason {
+    /// The opponent's master was captured.
+    Stone,
+    /// A master reached the opponent's pagoda.
+    Stream,
+}
+
+impl fmt::Display for WinReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                WinReason::Stone => "Way of the Stone",
+                WinReason::Stream => "Way of the Stream",
+            }
+        )
+    }
+}
+
+/// Who won and how, if anyone has won yet.
+pub fn win_reason(board: &Board) -> Option<(PieceColour, WinReason)> {
+    let winner = winner(board)?;
+
+    let reason = if get_master_index(board, winner.opponent()).is_none() {
+        WinReason::Stone
+    } else {
+        WinReason::Stream
+    };
+
+    Some((winner, reason))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    /// In PGN's "YYYY.MM.DD" form, with "?"s for any unknown parts.
+    pub date: String,
+    pub blue: String,
+    pub red: String,
+    pub seed: Option<usize>,
+    pub start: GameState,
+    pub history: History,
+    /// How the game looks after everything in `history`.
+    pub game: GameState,
+}
+
+impl GameRecord {
+    pub fn new(state: &State, date: String, blue: String, red: String) -> Self {
+        let saved = SavedGame::new(state);
+
+        GameRecord {
+            date,
+            blue,
+            red,
+            seed: Some(saved.seed),
+            start: saved.start,
+            history: saved.history,
+            game: saved.game,
+        }
+    }
+
+    pub fn result(&self) -> Option<(PieceColour, WinReason)> {
+        win_reason(&self.game.board)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordError(pub String);
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+pub fn export(record: &GameRecord, path: &Path) -> Result<(), RecordError> {
+    fs::write(path, record.to_string())
+        .map_err(|e| RecordError(format!("could not write {}: {}", path.display(), e)))
+}
+
+pub fn import(path: &Path) -> Result<GameRecord, RecordError> {
+    fs::read_to_string(path)
+        .map_err(|e| RecordError(format!("could not read {}: {}", path.display(), e)))?
+        .parse()
+}
+
+/// Turns seconds since the unix epoch into a PGN style date.
+pub fn date_from_unix(seconds: u64) -> String {
+    //Howard Hinnant's `civil_from_days`
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 -
+                           day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+fn deal_text(game: &GameState) -> String {
+    format!(
+        "Blue: {}, {}; Red: {}, {}; Center: {}",
+        game.blue_cards.0,
+        game.blue_cards.1,
+        game.red_cards.0,
+        game.red_cards.1,
+        game.center_card
+    )
+}
+
+fn parse_deal(text: &str) -> Result<GameState, RecordError> {
+    let bad_deal = || {
+        RecordError(format!(
+            "\"{}\" is not a deal like \"Blue: Tiger, Crab; Red: Monkey, Frog; Center: Rabbit\"",
+            text
+        ))
+    };
+
+    let mut blue_cards = None;
+    let mut red_cards = None;
+    let mut center_card = None;
+
+    for part in text.split(';') {
+        let mut split = part.splitn(2, ':');
+        let (name, cards) = match (split.next(), split.next()) {
+            (Some(name), Some(cards)) => (name.trim(), cards),
+            _ => return Err(bad_deal()),
+        };
+
+        let cards = cards
+            .split(',')
+            .map(|card| card.trim().parse())
+            .collect::<Result<Vec<Card>, _>>()
+            .map_err(|e| RecordError(e.to_string()))?;
+
+        match (name.to_ascii_lowercase().as_str(), cards.as_slice()) {
+            ("blue", &[first, second]) => blue_cards = Some((first, second)),
+            ("red", &[first, second]) => red_cards = Some((first, second)),
+            ("center", &[card]) => center_card = Some(card),
+            _ => return Err(bad_deal()),
+        }
+    }
+
+    match (blue_cards, red_cards, center_card) {
+        (Some(blue_cards), Some(red_cards), Some(center_card)) => {
+            Ok(GameState {
+                board: starting_board(),
+                blue_cards,
+                red_cards,
+                center_card,
+                to_move: center_card.stamp(),
+            })
+        }
+        _ => Err(bad_deal()),
+    }
+}
+
+const LINE_LENGTH: usize = 80;
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let result = self.result();
+
+        writeln!(f, "[Date \"{}\"]", self.date)?;
+        writeln!(f, "[Blue \"{}\"]", self.blue)?;
+        writeln!(f, "[Red \"{}\"]", self.red)?;
+        match self.seed {
+            Some(seed) => writeln!(f, "[Seed \"{}\"]", seed)?,
+            None => writeln!(f, "[Seed \"?\"]")?,
+        }
+        writeln!(f, "[Deal \"{}\"]", deal_text(&self.start))?;
+        if self.start.board != starting_board() ||
+            self.start.to_move != self.start.center_card.stamp()
+        {
+            writeln!(f, "[Position \"{}\"]", self.start)?;
+        }
+        match result {
+            Some((winner, reason)) => {
+                writeln!(f, "[Result \"{}\"]", winner)?;
+                writeln!(f, "[Reason \"{}\"]", reason)?;
+            }
+            None => writeln!(f, "[Result \"*\"]")?,
+        }
+        writeln!(f)?;
+
+        let mut line = String::new();
+
+        for (i, record) in self.history.records().iter().enumerate() {
+            let mut word = String::new();
+
+            if i % 2 == 0 {
+                word.push_str(&format!("{}. ", i / 2 + 1));
+            }
+            word.push_str(&Notation::from(record).to_string());
+
+            if !line.is_empty() && line.len() + 1 + word.len() > LINE_LENGTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+
+        if !line.is_empty() {
+            writeln!(f, "{}", line)?;
+        }
+
+        match result {
+            Some((winner, _)) => writeln!(f, "{}", winner),
+            None => writeln!(f, "*"),
+        }
+    }
+}
+
+/// Splits a header line like `[Key "Value"]` into the key and the value.
+fn parse_header(line: &str) -> Option<(&str, &str)> {
+    let inside = line.strip_prefix('[')?.strip_suffix(']')?;
+    let mut split = inside.splitn(2, ' ');
+    let key = split.next()?;
+    let value = split.next()?.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    Some((key, value))
+}
+
+fn is_move_word(word: &str) -> bool {
+    word.eq_ignore_ascii_case("pass") || word.contains('-')
+}
+
+fn is_turn_number(word: &str) -> bool {
+    word.ends_with('.') && word.trim_end_matches('.').chars().all(|c| c.is_ascii_digit())
+}
+
+impl FromStr for GameRecord {
+    type Err = RecordError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut date = "????.??.??".to_string();
+        let mut blue = "?".to_string();
+        let mut red = "?".to_string();
+        let mut seed = None;
+        let mut start = None;
+        let mut position = None;
+        let mut claimed_result = None;
+        let mut claimed_reason = None;
+
+        let mut lines = text.lines().map(str::trim).peekable();
+
+        while let Some(line) = lines.peek().cloned() {
+            if line.is_empty() {
+                lines.next();
+                continue;
+            }
+
+            if !line.starts_with('[') {
+                break;
+            }
+            lines.next();
+
+            let (key, value) = parse_header(line).ok_or_else(|| {
+                RecordError(format!("\"{}\" is not a header like [Key \"Value\"]", line))
+            })?;
+
+            match key {
+                "Date" => date = value.to_string(),
+                "Blue" => blue = value.to_string(),
+                "Red" => red = value.to_string(),
+                "Seed" => seed = value.parse().ok(),
+                "Deal" => start = Some(parse_deal(value)?),
+                "Position" => {
+                    position = Some(value.parse::<GameState>().map_err(|e| {
+                        RecordError(format!("bad Position header: {}", e))
+                    })?)
+                }
+                "Result" => claimed_result = Some(value.to_string()),
+                "Reason" => claimed_reason = Some(value.to_string()),
+                //like PGN, headers we don't know about are allowed
+                _ => {}
+            }
+        }
+
+        let start = position.or(start).ok_or_else(|| {
+            RecordError("there is no Deal or Position header".to_string())
+        })?;
+
+        let mut game = start;
+        let mut history = History::new();
+        let mut words: Vec<&str> = Vec::new();
+        let mut ply_number = 0;
+
+        for word in lines.flat_map(str::split_whitespace) {
+            if words.is_empty() && is_turn_number(word) {
+                continue;
+            }
+
+            words.push(word);
+
+            if !is_move_word(word) {
+                continue;
+            }
+
+            ply_number += 1;
+
+            let ply = words
+                .join(" ")
+                .parse::<Notation>()
+                .and_then(|notation| notation.to_ply(&game))
+                .map_err(|e| {
+                    RecordError(format!("ply {} ({}): {}", ply_number, words.join(" "), e))
+                })?;
+
+            history.play(&mut game, ply);
+            words.clear();
+        }
+
+        let result = win_reason(&game.board);
+
+        let actual_result = match result {
+            Some((winner, _)) => winner.to_string(),
+            None => "*".to_string(),
+        };
+
+        let final_word = words.join(" ");
+        let claimed = claimed_result.unwrap_or_else(|| actual_result.clone());
+
+        if !final_word.is_empty() && final_word != claimed {
+            return Err(RecordError(format!(
+                "expected the result \"{}\" after the last ply but found \"{}\"",
+                claimed,
+                final_word
+            )));
+        }
+
+        if claimed != actual_result {
+            return Err(RecordError(format!(
+                "the record says the result is \"{}\" but the plies give \"{}\"",
+                claimed,
+                actual_result
+            )));
+        }
+
+        if let (Some(claimed), Some((_, reason))) = (claimed_reason, result) {
+            if claimed != reason.to_string() {
+                return Err(RecordError(format!(
+                    "the record says the game was won by \"{}\" but it was won by \"{}\"",
+                    claimed,
+                    reason
+                )));
+            }
+        }
+
+        Ok(GameRecord {
+            date,
+            blue,
+            red,
+            seed,
+            start,
+            history,
+            game,
+        })
+    }
+}
+
+#[cfg(test)]
+mod records {
+    use record::*;
+    use rand::{Rng, SeedableRng, StdRng};
+
+    fn random_record(seed: usize) -> GameRecord {
+        let seed_slice: &[_] = &[seed];
+        let mut rng: StdRng = SeedableRng::from_seed(seed_slice);
+
+        let start = GameState::deal(&mut rng);
+        let mut game = start;
+        let mut history = History::new();
+
+        for _ in 0..200 {
+            match rng.choose(&game.legal_plies()) {
+                Some(&ply) => history.play(&mut game, ply),
+                None => break,
+            }
+        }
+
+        GameRecord {
+            date: "2026.10.18".to_string(),
+            blue: "Player".to_string(),
+            red: "CPU".to_string(),
+            seed: Some(seed),
+            start,
+            history,
+            game,
+        }
+    }
+
+    quickcheck! {
+        fn records_import_back_the_same(seed: usize) -> bool {
+            let record = random_record(seed);
+
+            record.to_string().parse() == Ok(record)
+        }
+    }
+
+    #[test]
+    fn dates_come_out_in_pgn_form() {
+        assert_eq!(date_from_unix(0), "1970.01.01");
+        assert_eq!(date_from_unix(951_782_400), "2000.02.29");
+        assert_eq!(date_from_unix(1_700_000_000), "2023.11.14");
+    }
+
+    const SHORT_GAME: &str = "[Date \"2026.10.18\"]
+[Blue \"Player\"]
+[Red \"CPU\"]
+[Seed \"42\"]
+[Deal \"Blue: Tiger, Crab; Red: Monkey, Frog; Center: Rabbit\"]
+[Result \"*\"]
+
+1. Tiger c5-c3 Monkey b1-c2
+*
+";
+
+    #[test]
+    fn a_written_out_record_imports() {
+        let record: GameRecord = SHORT_GAME.parse().unwrap();
+
+        assert_eq!(record.history.len(), 2);
+        assert_eq!(record.to_string(), SHORT_GAME);
+    }
+
+    #[test]
+    fn every_ply_is_checked() {
+        let illegal = SHORT_GAME.replace("b1-c2", "b1-b2");
+
+        assert_eq!(
+            illegal.parse::<GameRecord>(),
+            Err(RecordError(
+                "ply 2 (Monkey b1-b2): Monkey b1-b2 is not a legal ply here".to_string(),
+            ))
+        );
+    }
+
+    #[test]
+    fn the_result_must_match_the_plies() {
+        let wrong = SHORT_GAME.replace("[Result \"*\"]", "[Result \"Red\"]");
+
+        assert!(wrong.parse::<GameRecord>().is_err());
+    }
+
+    #[test]
+    fn other_starting_positions_are_kept() {
+        let mut record: GameRecord = SHORT_GAME.parse().unwrap();
+        record.start = "2R2/5/5/1r3/1bB2 Tiger,Crab Monkey,Frog Rabbit r".parse().unwrap();
+        record.history = History::new();
+        record.game = record.start;
+
+        let text = record.to_string();
+
+        assert!(text.contains("[Position \"2R2/5/5/1r3/1bB2 Tiger,Crab Monkey,Frog Rabbit r\"]"));
+        assert_eq!(text.parse(), Ok(record));
+    }
+
+    #[test]
+    fn finished_games_give_a_reason() {
+        let record = (0..)
+            .map(random_record)
+            .find(|record| record.result().is_some())
+            .unwrap();
+
+        let text = record.to_string();
+
+        assert!(text.contains("[Reason \"Way of the"));
+    }
+}
@@ -0,0 +1,184 @@
+//! Stepping back and forth through a recorded game.
+
+use std::time::{Duration, Instant};
+
+use ::*;
+use history::Record;
+use record::GameRecord;
+
+/// How long autoplay waits between plies at each speed, slowest first.
+pub const DELAYS: [Duration; 4] = [
+    Duration::from_millis(2000),
+    Duration::from_millis(1000),
+    Duration::from_millis(500),
+    Duration::from_millis(250),
+];
+
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub record: GameRecord,
+    /// How many of the record's plies have been played out.
+    pub shown: usize,
+    pub autoplay: bool,
+    /// An index into `DELAYS`.
+    pub speed: usize,
+    last_step: Option<Instant>,
+}
+
+impl Replay {
+    pub fn new(record: GameRecord) -> Self {
+        Replay {
+            record,
+            shown: 0,
+            autoplay: false,
+            speed: 1,
+            last_step: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.record.history.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.record.history.is_empty()
+    }
+
+    pub fn position(&self) -> GameState {
+        self.record.history.position_after(&self.record.game, self.shown)
+    }
+
+    /// The ply that led to the current position, if any.
+    pub fn last_ply(&self) -> Option<&Record> {
+        self.shown
+            .checked_sub(1)
+            .and_then(|i| self.record.history.records().get(i))
+    }
+
+    pub fn forward(&mut self) {
+        self.shown = std::cmp::min(self.shown + 1, self.len());
+    }
+
+    pub fn back(&mut self) {
+        self.shown = self.shown.saturating_sub(1);
+    }
+
+    pub fn to_start(&mut self) {
+        self.shown = 0;
+    }
+
+    pub fn to_end(&mut self) {
+        self.shown = self.len();
+    }
+
+    pub fn delay(&self) -> Duration {
+        DELAYS[self.speed]
+    }
+
+    pub fn next_speed(&mut self) {
+        self.speed = (self.speed + 1) % DELAYS.len();
+    }
+
+    /// Starting autoplay at the end goes back to the start first.
+    pub fn toggle_autoplay(&mut self, now: Instant) {
+        self.autoplay = !self.autoplay;
+
+        if self.autoplay {
+            if self.shown == self.len() {
+                self.shown = 0;
+            }
+
+            self.last_step = Some(now);
+        }
+    }
+
+    /// Call this every frame. Steps forward if autoplay is on and it has
+    /// been long enough, and turns autoplay off at the end.
+    pub fn update(&mut self, now: Instant) {
+        if !self.autoplay {
+            return;
+        }
+
+        let due = match self.last_step {
+            Some(last_step) => now.duration_since(last_step) >= self.delay(),
+            None => true,
+        };
+
+        if due {
+            self.forward();
+            self.last_step = Some(now);
+        }
+
+        if self.shown == self.len() {
+            self.autoplay = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod replaying {
+    use replay::*;
+    use history::History;
+
+    fn replay() -> Replay {
+        let start = GameState {
+            board: starting_board(),
+            blue_cards: (Tiger, Crab),
+            red_cards: (Monkey, Frog),
+            center_card: Rabbit,
+            to_move: Blue,
+        };
+        let mut game = start;
+        let mut history = History::new();
+
+        for _ in 0..4 {
+            let ply = game.legal_plies()[0];
+            history.play(&mut game, ply);
+        }
+
+        Replay::new(GameRecord {
+            date: "2026.10.18".to_string(),
+            blue: "Blue".to_string(),
+            red: "Red".to_string(),
+            seed: None,
+            start,
+            history,
+            game,
+        })
+    }
+
+    #[test]
+    fn stepping_stays_within_the_game() {
+        let mut replay = replay();
+
+        replay.back();
+        assert_eq!(replay.shown, 0);
+        assert_eq!(replay.position(), replay.record.start);
+        assert_eq!(replay.last_ply(), None);
+
+        replay.to_end();
+        replay.forward();
+        assert_eq!(replay.shown, 4);
+        assert_eq!(replay.position(), replay.record.game);
+        assert_eq!(replay.last_ply(), replay.record.history.records().last());
+    }
+
+    #[test]
+    fn autoplay_waits_for_the_delay_and_stops_at_the_end() {
+        let mut replay = replay();
+        let start = Instant::now();
+
+        replay.toggle_autoplay(start);
+        replay.update(start);
+        assert_eq!(replay.shown, 0);
+
+        let mut now = start;
+        for expected in 1..5 {
+            now += replay.delay();
+            replay.update(now);
+            assert_eq!(replay.shown, expected);
+        }
+
+        assert!(!replay.autoplay);
+    }
+}
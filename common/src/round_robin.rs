@@ -0,0 +1,349 @@
+//! Playing every entrant against every other one and rating them all on the
+//! Elo scale, so changes to the CPU can be checked against several others at
+//! once.
+
+use ::*;
+use tournament::{run_match, Entrant, MatchReport, MatchSettings, Score};
+
+/// How many drawn games each pair of entrants that met is assumed to have
+/// played as well, as BayesElo does. Without these anyone who won or lost
+/// every game would have an infinite rating.
+const PRIOR_DRAWS: f64 = 2.0;
+
+const MAX_ITERATIONS: usize = 10_000;
+const TOLERANCE: f64 = 1e-10;
+
+/// The results between each pair of entrants.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundRobin {
+    pub names: Vec<String>,
+    /// `scores[i][j]` is how the `i`th entrant did against the `j`th.
+    pub scores: Vec<Vec<Score>>,
+}
+
+impl RoundRobin {
+    pub fn new(names: Vec<String>) -> Self {
+        let count = names.len();
+
+        RoundRobin {
+            names,
+            scores: vec![vec![Score::default(); count]; count],
+        }
+    }
+
+    /// Adds the results of a match between the `a`th and `b`th entrants, where
+    /// `a` was the first player in the match.
+    pub fn add(&mut self, a: usize, b: usize, report: &MatchReport) {
+        let total = report.total();
+
+        self.scores[a][b] = self.scores[a][b] + total;
+        self.scores[b][a] = self.scores[b][a] + total.flipped();
+    }
+
+    /// How the `i`th entrant did against everyone.
+    pub fn total(&self, i: usize) -> Score {
+        self.scores[i]
+            .iter()
+            .fold(Score::default(), |total, &score| total + score)
+    }
+
+    /// Each entrant's rating, averaging zero. These are the maximum likelihood
+    /// ratings under the Bradley-Terry model with draws counted as half a win,
+    /// after adding `PRIOR_DRAWS`, found with Hunter's MM algorithm. A rating
+    /// 400 points above another means winning about ten games for each loss.
+    pub fn ratings(&self) -> Vec<f64> {
+        let count = self.names.len();
+
+        let games = |i: usize, j: usize| {
+            let played = self.scores[i][j].games() as f64;
+            if played > 0.0 {
+                played + PRIOR_DRAWS
+            } else {
+                0.0
+            }
+        };
+        let points: Vec<f64> = (0..count)
+            .map(|i| {
+                (0..count)
+                    .filter(|&j| self.scores[i][j].games() > 0)
+                    .map(|j| self.scores[i][j].points() + PRIOR_DRAWS / 2.0)
+                    .sum()
+            })
+            .collect();
+
+        let mut strengths = vec![1.0; count];
+
+        for _ in 0..MAX_ITERATIONS {
+            let mut next: Vec<f64> = (0..count)
+                .map(|i| {
+                    let denominator: f64 = (0..count)
+                        .map(|j| games(i, j) / (strengths[i] + strengths[j]))
+                        .sum();
+
+                    if denominator > 0.0 {
+                        points[i] / denominator
+                    } else {
+                        1.0
+                    }
+                })
+                .collect();
+
+            //only the ratios matter, so keep the geometric mean at one
+            let log_mean = next.iter().map(|s| s.ln()).sum::<f64>() / count as f64;
+            for strength in next.iter_mut() {
+                *strength /= log_mean.exp();
+            }
+
+            let change = strengths
+                .iter()
+                .zip(next.iter())
+                .map(|(old, new)| (old - new).abs() / old)
+                .fold(0.0, f64::max);
+
+            strengths = next;
+
+            if change < TOLERANCE {
+                break;
+            }
+        }
+
+        strengths.iter().map(|s| 400.0 * s.log10()).collect()
+    }
+
+    /// The entrants' indices, highest rated first, along with their ratings.
+    pub fn standings(&self) -> Vec<(usize, f64)> {
+        let mut standings: Vec<(usize, f64)> = self.ratings().into_iter().enumerate().collect();
+
+        standings.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        standings
+    }
+
+    /// One row per entrant, highest rated first, with their points against
+    /// each opponent in the columns after their overall results.
+    pub fn csv(&self) -> String {
+        let standings = self.standings();
+
+        let mut header = vec![
+            "rank".to_string(),
+            "name".to_string(),
+            "elo".to_string(),
+            "games".to_string(),
+            "wins".to_string(),
+            "losses".to_string(),
+            "draws".to_string(),
+            "score".to_string(),
+        ];
+        header.extend(standings.iter().map(|&(j, _)| csv_field(&format!("vs {}", self.names[j]))));
+
+        let mut lines = vec![header.join(",")];
+
+        for (rank, &(i, rating)) in standings.iter().enumerate() {
+            let total = self.total(i);
+
+            let mut row = vec![
+                (rank + 1).to_string(),
+                csv_field(&self.names[i]),
+                format!("{:.1}", rating),
+                total.games().to_string(),
+                total.wins.to_string(),
+                total.losses.to_string(),
+                total.draws.to_string(),
+                format!("{:.4}", total.rate()),
+            ];
+            row.extend(standings.iter().map(|&(j, _)| {
+                if i == j {
+                    String::new()
+                } else {
+                    self.scores[i][j].points().to_string()
+                }
+            }));
+
+            lines.push(row.join(","));
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    /// A table of the standings, then a cross table of how each entrant did
+    /// against each other one.
+    pub fn markdown(&self) -> String {
+        let standings = self.standings();
+        let mut lines = vec![
+            "| Rank | Player | Elo | Games | Wins | Losses | Draws | Score | 95% CI |".to_string(),
+            "| ---: | --- | ---: | ---: | ---: | ---: | ---: | ---: | --- |".to_string(),
+        ];
+
+        for (rank, &(i, rating)) in standings.iter().enumerate() {
+            let total = self.total(i);
+            let (low, high) = total.confidence_interval();
+
+            lines.push(format!(
+                "| {} | {} | {:+.0} | {} | {} | {} | {} | {:.1}% | {:.1}% to {:.1}% |",
+                rank + 1,
+                markdown_cell(&self.names[i]),
+                rating,
+                total.games(),
+                total.wins,
+                total.losses,
+                total.draws,
+                total.rate() * 100.0,
+                low * 100.0,
+                high * 100.0
+            ));
+        }
+
+        lines.push(String::new());
+
+        let numbers: Vec<String> = (1..standings.len() + 1).map(|n| n.to_string()).collect();
+        lines.push(format!("| | {} |", numbers.join(" | ")));
+        lines.push(format!("| --- |{}", " ---: |".repeat(standings.len())));
+
+        for (rank, &(i, _)) in standings.iter().enumerate() {
+            let cells: Vec<String> = standings
+                .iter()
+                .map(|&(j, _)| {
+                    let score = self.scores[i][j];
+                    if i == j || score.games() == 0 {
+                        "-".to_string()
+                    } else {
+                        format!("{}/{}", score.points(), score.games())
+                    }
+                })
+                .collect();
+
+            lines.push(format!(
+                "| {}. {} | {} |",
+                rank + 1,
+                markdown_cell(&self.names[i]),
+                cells.join(" | ")
+            ));
+        }
+
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Quotes `field` if it would otherwise be split or misread.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn markdown_cell(cell: &str) -> String {
+    cell.replace('|', "\\|")
+}
+
+/// Plays a match with `settings` between every pair of `entrants`.
+/// `progress` is called after each match.
+pub fn round_robin<F>(
+    entrants: &mut [Entrant],
+    settings: &MatchSettings,
+    mut progress: F,
+) -> RoundRobin
+where
+    F: FnMut(&MatchReport),
+{
+    let mut results = RoundRobin::new(entrants.iter().map(|e| e.name.clone()).collect());
+
+    for b in 1..entrants.len() {
+        let (before, after) = entrants.split_at_mut(b);
+        let second = &mut after[0];
+
+        for (a, first) in before.iter_mut().enumerate() {
+            let report = run_match(first, second, settings, |_| {});
+
+            results.add(a, b, &report);
+            progress(&report);
+        }
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod rating {
+    use round_robin::*;
+    use tournament::RandomPlayer;
+
+    fn score(wins: usize, losses: usize, draws: usize) -> Score {
+        Score {
+            wins,
+            losses,
+            draws,
+        }
+    }
+
+    fn results(scores: &[(usize, usize, Score)], count: usize) -> RoundRobin {
+        let mut results = RoundRobin::new((0..count).map(|i| format!("Player {}", i)).collect());
+
+        for &(a, b, score) in scores {
+            results.scores[a][b] = score;
+            results.scores[b][a] = score.flipped();
+        }
+
+        results
+    }
+
+    #[test]
+    fn even_results_give_even_ratings() {
+        let results = results(&[(0, 1, score(5, 5, 2)), (1, 2, score(0, 0, 4))], 3);
+
+        for rating in results.ratings() {
+            assert!(rating.abs() < 1e-6, "{:?}", results.ratings());
+        }
+    }
+
+    #[test]
+    fn winning_three_quarters_is_about_two_hundred_points() {
+        let ratings = results(&[(0, 1, score(760, 240, 0))], 2).ratings();
+
+        assert!((ratings[0] - ratings[1] - 200.0).abs() < 5.0, "{:?}", ratings);
+        assert!((ratings[0] + ratings[1]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn winning_everything_gives_a_finite_rating() {
+        let ratings = results(&[(0, 1, score(10, 0, 0)), (1, 2, score(10, 0, 0))], 3).ratings();
+
+        assert!(ratings.iter().all(|r| r.is_finite()));
+        assert!(ratings[0] > ratings[1] && ratings[1] > ratings[2]);
+    }
+
+    #[test]
+    fn every_pair_plays_once() {
+        let mut entrants: Vec<Entrant> = (0..4).map(|_| Entrant::new(RandomPlayer)).collect();
+        let settings = MatchSettings {
+            games: 2,
+            ..MatchSettings::default()
+        };
+        let mut matches = 0;
+
+        let results = round_robin(&mut entrants, &settings, |_| matches += 1);
+
+        assert_eq!(matches, 6);
+        for i in 0..4 {
+            assert_eq!(results.total(i).games(), 6);
+            assert_eq!(results.scores[i][i].games(), 0);
+        }
+    }
+
+    #[test]
+    fn reports_list_the_highest_rated_first() {
+        let mut results = results(&[(0, 1, score(1, 3, 0))], 2);
+        results.names[1] = "Strong Minimax:student=100,mobility=3".to_string();
+
+        let csv = results.csv();
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("rank,name,elo,"));
+        assert!(lines.next().unwrap().starts_with("1,\"Strong Minimax:student=100,mobility=3\","));
+        assert!(lines.next().unwrap().starts_with("2,Player 0,"));
+
+        let markdown = results.markdown();
+        assert!(markdown.contains("| 1 | Strong Minimax:student=100,mobility=3 | +"));
+        assert!(markdown.contains("| 2. Player 0 | 1/4 | - |"));
+    }
+}
@@ -0,0 +1,548 @@
+//! Saving games as text files that people can read, and loading them back.
+//!
+//! The first line says which version of the format the file uses. When the
+//! format changes, bump `VERSION` and keep `SavedGame::from_str` able to read
+//! the older versions, filling in anything they did not record.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use ::*;
+use cpu::{CpuPlayer, Difficulty, Engine};
+use history::History;
+use notation::Notation;
+
+pub const VERSION: u32 = 2;
+const HEADER: &str = "onitama save";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedGame {
+    pub seed: usize,
+    pub cpu: CpuPlayer,
+    /// Version 1 saves did not record these, so they load as the defaults.
+    pub mode: Mode,
+    pub side: Side,
+    /// How the game started, before anything in `history`.
+    pub start: GameState,
+    pub history: History,
+    /// How the game looks after everything in `history`.
+    pub game: GameState,
+}
+
+impl SavedGame {
+    /// Plies that were undone and could still be redone are not kept.
+    pub fn new(state: &State) -> Self {
+        let mut history = History::new();
+        let start = state.history.position_after(&state.game, 0);
+        let mut game = start;
+
+        for record in state.history.records() {
+            history.play(&mut game, record.ply);
+        }
+
+        SavedGame {
+            seed: state.seed,
+            cpu: state.cpu,
+            mode: state.mode,
+            side: state.side,
+            start,
+            history,
+            game,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveError {
+    /// Which line of the file the problem is on, counting from one, if it is
+    /// on any one line.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+fn error_at(line: usize, message: String) -> SaveError {
+    SaveError {
+        line: Some(line),
+        message,
+    }
+}
+
+pub fn save(state: &State, path: &Path) -> Result<(), SaveError> {
+    fs::write(path, SavedGame::new(state).to_string()).map_err(|e| {
+        SaveError {
+            line: None,
+            message: format!("could not write {}: {}", path.display(), e),
+        }
+    })
+}
+
+pub fn load(path: &Path) -> Result<SavedGame, SaveError> {
+    let text = fs::read_to_string(path).map_err(|e| {
+        SaveError {
+            line: None,
+            message: format!("could not read {}: {}", path.display(), e),
+        }
+    })?;
+
+    text.parse()
+}
+
+fn write_position(f: &mut fmt::Formatter, game: &GameState) -> fmt::Result {
+    for row in game.board.chunks(5) {
+        let letters: String = row.iter()
+            .map(|square| square.map(Piece::letter).unwrap_or('.'))
+            .collect();
+
+        writeln!(f, "{}", letters)?;
+    }
+
+    writeln!(f, "blue: {}, {}", game.blue_cards.0, game.blue_cards.1)?;
+    writeln!(f, "red: {}, {}", game.red_cards.0, game.red_cards.1)?;
+    writeln!(f, "center: {}", game.center_card)?;
+    writeln!(f, "to move: {}", game.to_move)
+}
+
+impl fmt::Display for SavedGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        writeln!(f, "seed: {}", self.seed)?;
+        writeln!(f, "difficulty: {}", self.cpu.difficulty)?;
+        writeln!(f, "engine: {}", self.cpu.engine)?;
+        writeln!(f, "mode: {}", self.mode)?;
+        if let Mode::VsCpu(player) = self.mode {
+            writeln!(f, "player: {}", player)?;
+        }
+        writeln!(f, "side: {}", self.side)?;
+
+        writeln!(f, "\nstart:")?;
+        write_position(f, &self.start)?;
+
+        writeln!(f, "\nhistory:")?;
+        for record in self.history.records() {
+            writeln!(f, "{} {}", record.colour, Notation::from(record))?;
+        }
+
+        writeln!(f, "\nnow:")?;
+        write_position(f, &self.game)
+    }
+}
+
+/// The non-blank lines of a file, with their line numbers.
+struct Lines<'a> {
+    lines: Vec<(usize, &'a str)>,
+    next: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn new(text: &'a str) -> Self {
+        Lines {
+            lines: text.lines()
+                .enumerate()
+                .map(|(i, line)| (i + 1, line.trim()))
+                .filter(|&(_, line)| !line.is_empty())
+                .collect(),
+            next: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.lines.get(self.next).map(|&(_, line)| line)
+    }
+
+    fn next_line(&mut self) -> Result<(usize, &'a str), SaveError> {
+        let result = self.lines.get(self.next).cloned().ok_or_else(|| {
+            SaveError {
+                line: None,
+                message: "the file ends too early".to_string(),
+            }
+        })?;
+
+        self.next += 1;
+
+        Ok(result)
+    }
+
+    /// Reads a line like "key: value" and returns the value.
+    fn value(&mut self, key: &str) -> Result<(usize, &'a str), SaveError> {
+        let (number, line) = self.next_line()?;
+
+        let mut split = line.splitn(2, ':');
+
+        match (split.next(), split.next()) {
+            (Some(name), Some(value)) if name.trim().eq_ignore_ascii_case(key) => {
+                Ok((number, value.trim()))
+            }
+            _ => Err(error_at(
+                number,
+                format!("expected \"{}:\" but found \"{}\"", key, line),
+            )),
+        }
+    }
+
+    fn heading(&mut self, key: &str) -> Result<(), SaveError> {
+        let (number, value) = self.value(key)?;
+
+        if value.is_empty() {
+            Ok(())
+        } else {
+            Err(error_at(
+                number,
+                format!("expected nothing after \"{}:\" but found \"{}\"", key, value),
+            ))
+        }
+    }
+
+    /// Reads a line like "key: value" where the value is the `Display` text
+    /// of one of `T::all_values()`.
+    fn named<T: AllValues + fmt::Display>(&mut self, key: &str) -> Result<T, SaveError> {
+        let (number, value) = self.value(key)?;
+
+        T::all_values()
+            .into_iter()
+            .find(|v| v.to_string().eq_ignore_ascii_case(value))
+            .ok_or_else(|| {
+                let options: Vec<String> = T::all_values().iter().map(|v| v.to_string()).collect();
+
+                error_at(
+                    number,
+                    format!(
+                        "\"{}\" is not a {}, expected one of {}",
+                        value,
+                        key,
+                        options.join(", ")
+                    ),
+                )
+            })
+    }
+
+    /// Reads the "mode:" line, and then the "player:" line if the player is
+    /// against the CPU.
+    fn mode(&mut self) -> Result<Mode, SaveError> {
+        let (number, value) = self.value("mode")?;
+        let modes = [Mode::VsCpu(Blue), Mode::HotSeat, Mode::Spectate];
+
+        match modes.iter().find(|mode| mode.to_string().eq_ignore_ascii_case(value)) {
+            Some(&Mode::VsCpu(_)) => Ok(Mode::VsCpu(self.colour("player")?)),
+            Some(&mode) => Ok(mode),
+            None => {
+                let options: Vec<String> = modes.iter().map(|mode| mode.to_string()).collect();
+
+                Err(error_at(
+                    number,
+                    format!("\"{}\" is not a mode, expected one of {}", value, options.join(", ")),
+                ))
+            }
+        }
+    }
+
+    fn card(&mut self, key: &str) -> Result<Card, SaveError> {
+        let (number, value) = self.value(key)?;
+
+        value.parse().map_err(|e: UnknownCard| error_at(number, e.to_string()))
+    }
+
+    fn card_pair(&mut self, key: &str) -> Result<(Card, Card), SaveError> {
+        let (number, value) = self.value(key)?;
+
+        let cards = value
+            .split(',')
+            .map(|name| name.trim().parse())
+            .collect::<Result<Vec<Card>, _>>()
+            .map_err(|e| error_at(number, e.to_string()))?;
+
+        match cards.as_slice() {
+            &[first, second] => Ok((first, second)),
+            _ => Err(error_at(
+                number,
+                format!("expected two cards separated by a comma but found \"{}\"", value),
+            )),
+        }
+    }
+
+    fn colour(&mut self, key: &str) -> Result<PieceColour, SaveError> {
+        let (number, value) = self.value(key)?;
+
+        PieceColour::from_name(value).ok_or_else(|| {
+            error_at(number, format!("\"{}\" is not Red or Blue", value))
+        })
+    }
+
+    fn position(&mut self) -> Result<GameState, SaveError> {
+        let mut board = [None; 25];
+
+        for y in 0..5 {
+            let (number, row) = self.next_line()?;
+
+            let letters: Vec<char> = row.chars().filter(|c| !c.is_whitespace()).collect();
+
+            if letters.len() != 5 {
+                return Err(error_at(
+                    number,
+                    format!("expected a row of five squares like \"rrRrr\" but found \"{}\"", row),
+                ));
+            }
+
+            for (x, &letter) in letters.iter().enumerate() {
+                board[y * 5 + x] = match letter {
+                    '.' => None,
+                    _ => {
+                        Some(Piece::from_letter(letter).ok_or_else(|| {
+                            error_at(
+                                number,
+                                format!(
+                                    "'{}' is not a piece, expected one of r R b B w or .",
+                                    letter
+                                ),
+                            )
+                        })?)
+                    }
+                };
+            }
+        }
+
+        Ok(GameState {
+            board,
+            blue_cards: self.card_pair("blue")?,
+            red_cards: self.card_pair("red")?,
+            center_card: self.card("center")?,
+            to_move: self.colour("to move")?,
+        })
+    }
+}
+
+fn read_save(lines: &mut Lines, version: u32) -> Result<SavedGame, SaveError> {
+    let (number, seed) = lines.value("seed")?;
+    let seed = seed.parse().map_err(|e| {
+        error_at(number, format!("\"{}\" is not a seed: {}", seed, e))
+    })?;
+
+    let cpu = CpuPlayer {
+        difficulty: lines.named::<Difficulty>("difficulty")?,
+        engine: lines.named::<Engine>("engine")?,
+    };
+
+    let (mode, side) = if version >= 2 {
+        (lines.mode()?, lines.named::<Side>("side")?)
+    } else {
+        (Mode::default(), Side::default())
+    };
+
+    lines.heading("start")?;
+    let start = lines.position()?;
+
+    lines.heading("history")?;
+
+    let mut game = start;
+    let mut history = History::new();
+
+    while lines.peek().is_some_and(|line| !line.contains(':')) {
+        let (number, line) = lines.next_line()?;
+
+        let mut split = line.splitn(2, ' ');
+        let colour = split.next().and_then(PieceColour::from_name);
+        let rest = split.next().unwrap_or("");
+
+        if colour != Some(game.to_move) {
+            return Err(error_at(
+                number,
+                format!("expected a ply by {} like \"{} Tiger c5-c3\"", game.to_move, game.to_move),
+            ));
+        }
+
+        let ply = rest.parse::<Notation>()
+            .and_then(|notation| notation.to_ply(&game))
+            .map_err(|e| error_at(number, e.to_string()))?;
+
+        history.play(&mut game, ply);
+    }
+
+    lines.heading("now")?;
+    let now = lines.position()?;
+
+    if now != game {
+        return Err(SaveError {
+            line: None,
+            message: "the position under \"now:\" is not where the history leads".to_string(),
+        });
+    }
+
+    if let Ok((number, line)) = lines.next_line() {
+        return Err(error_at(number, format!("unexpected \"{}\" after the position", line)));
+    }
+
+    Ok(SavedGame {
+        seed,
+        cpu,
+        mode,
+        side,
+        start,
+        history,
+        game,
+    })
+}
+
+impl FromStr for SavedGame {
+    type Err = SaveError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = Lines::new(text);
+
+        let (number, header) = lines.next_line()?;
+
+        let version = header
+            .strip_prefix(HEADER)
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or_else(|| error_at(number, "this is not an onitama save".to_string()))?;
+
+        match version {
+            1..=VERSION => read_save(&mut lines, version),
+            _ => Err(error_at(
+                number,
+                format!(
+                    "this save uses format {}, but only formats up to {} can be loaded",
+                    version,
+                    VERSION
+                ),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod saving {
+    use save::*;
+    use rand::{Rng, SeedableRng, StdRng};
+
+    fn random_save(seed: usize) -> SavedGame {
+        let seed_slice: &[_] = &[seed];
+        let mut rng: StdRng = SeedableRng::from_seed(seed_slice);
+
+        let start = GameState::deal(&mut rng);
+        let mut game = start;
+        let mut history = History::new();
+
+        for _ in 0..rng.gen_range(0, 60) {
+            match rng.choose(&game.legal_plies()) {
+                Some(&ply) => history.play(&mut game, ply),
+                None => break,
+            }
+        }
+
+        let modes = [Mode::VsCpu(Blue), Mode::VsCpu(Red), Mode::HotSeat, Mode::Spectate];
+
+        SavedGame {
+            seed,
+            cpu: CpuPlayer::default(),
+            mode: *rng.choose(&modes).unwrap(),
+            side: *rng.choose(&Side::all_values()).unwrap(),
+            start,
+            history,
+            game,
+        }
+    }
+
+    quickcheck! {
+        fn saves_load_back_the_same(seed: usize) -> bool {
+            let saved = random_save(seed);
+
+            saved.to_string().parse() == Ok(saved)
+        }
+    }
+
+    //If this stops loading, then so will everyone's version 1 saves.
+    const VERSION_1: &str = "onitama save 1
+seed: 42
+difficulty: Casual
+engine: MCTS
+
+start:
+rrRrr
+.....
+.....
+.....
+bbBbb
+blue: Tiger, Crab
+red: Monkey, Frog
+center: Rabbit
+to move: Blue
+
+history:
+Blue Tiger c5-c3
+Red Monkey b1-c2
+
+now:
+r.Rrr
+..r..
+..B..
+.....
+bb.bb
+blue: Rabbit, Crab
+red: Tiger, Frog
+center: Monkey
+to move: Blue
+";
+
+    #[test]
+    fn version_1_saves_load() {
+        let saved: SavedGame = VERSION_1.parse().unwrap();
+
+        assert_eq!(saved.seed, 42);
+        assert_eq!(saved.cpu.difficulty, Difficulty::Casual);
+        assert_eq!(saved.cpu.engine, Engine::Mcts);
+        assert_eq!(saved.history.len(), 2);
+        assert_eq!(saved.mode, Mode::default());
+        assert_eq!(saved.side, Side::default());
+        assert_eq!(saved.to_string().parse(), Ok(saved));
+    }
+
+    #[test]
+    fn games_played_as_red_load_as_red() {
+        let saved = SavedGame {
+            mode: Mode::VsCpu(Red),
+            side: Side::Red,
+            ..random_save(3)
+        };
+        let text = saved.to_string();
+
+        assert!(text.contains("mode: vs CPU\nplayer: Red\nside: Red\n"));
+
+        let loaded: SavedGame = text.parse().unwrap();
+        assert_eq!(loaded.mode, Mode::VsCpu(Red));
+        assert_eq!(loaded.side, Side::Red);
+        assert_eq!(loaded, saved);
+    }
+
+    #[test]
+    fn errors_say_where_the_problem_is() {
+        let error = |s: &str| s.parse::<SavedGame>().unwrap_err();
+
+        assert_eq!(error("").line, None);
+        assert_eq!(error("not a save").line, Some(1));
+        assert_eq!(error("onitama save 99").line, Some(1));
+
+        let text = random_save(7).to_string();
+        let bad_seed = text.replacen("seed: 7", "seed: seven", 1);
+        assert_eq!(error(&bad_seed).line, Some(2));
+
+        let bad_piece = text.replacen("rrRrr", "rrXrr", 1);
+        assert!(error(&bad_piece).message.contains("r R b B w or ."));
+    }
+
+    #[test]
+    fn illegal_plies_are_rejected() {
+        let text = VERSION_1.replace("Red Monkey b1-c2", "Red Monkey b1-b2");
+
+        assert_eq!(text.parse::<SavedGame>().unwrap_err().line, Some(19));
+    }
+}
@@ -0,0 +1,544 @@
+//! Negamax search with alpha-beta pruning and iterative deepening, used to
+//! decide the CPU player's moves.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use ::*;
+use bitboard::*;
+use transposition::{Bound, Entry, TranspositionTable};
+
+pub type Score = i32;
+
+/// The score for winning on the spot. Wins further in the future score
+/// slightly less so the search prefers the quickest win and the slowest loss.
+pub const WIN_SCORE: Score = 1_000_000;
+const WIN_THRESHOLD: Score = WIN_SCORE - 1000;
+const INFINITY: Score = WIN_SCORE + 1;
+
+/// How much each part of the evaluation is worth.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Weights {
+    /// per student still on the board
+    pub student: Score,
+    /// penalty for having your master attackable
+    pub master_safety: Score,
+    /// per square the master is closer to the opponent's pagoda
+    pub pagoda_distance: Score,
+    /// per move available with the cards in hand
+    pub mobility: Score,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            student: 100,
+            master_safety: 60,
+            pagoda_distance: 8,
+            mobility: 3,
+        }
+    }
+}
+
+/// The largest weight `FromStr` accepts. Anything much bigger could overflow
+/// an evaluation or push it past `WIN_THRESHOLD`, so that a material edge would
+/// look like a forced win.
+pub const MAX_WEIGHT: Score = 1000;
+
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "student={},master-safety={},pagoda-distance={},mobility={}",
+            self.student,
+            self.master_safety,
+            self.pagoda_distance,
+            self.mobility
+        )
+    }
+}
+
+impl FromStr for Weights {
+    type Err = String;
+
+    /// Reads comma separated changes to the default weights, like
+    /// "student=120,mobility=5". Underscores may be used in place of dashes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Weights::default();
+
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let mut halves = part.splitn(2, '=');
+            let name = halves.next().unwrap_or("").trim().replace('_', "-");
+            let value = halves.next().ok_or_else(|| format!("\"{}\" has no value", part))?;
+            let value: Score = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("\"{}\" is not a whole number", value.trim()))?;
+
+            if !(0..=MAX_WEIGHT).contains(&value) {
+                return Err(format!(
+                    "{} is set to {}, but weights must be from 0 to {}",
+                    name, value, MAX_WEIGHT
+                ));
+            }
+
+            let weight = match name.to_lowercase().as_str() {
+                "student" => &mut weights.student,
+                "master-safety" => &mut weights.master_safety,
+                "pagoda-distance" => &mut weights.pagoda_distance,
+                "mobility" => &mut weights.mobility,
+                _ => {
+                    return Err(format!(
+                        "there is no weight called \"{}\", expected student, master-safety, \
+                         pagoda-distance or mobility",
+                        name
+                    ))
+                }
+            };
+            *weight = value;
+        }
+
+        Ok(weights)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Limits {
+    pub max_depth: u32,
+    /// The search stops early and uses the deepest completed iteration when
+    /// this runs out.
+    pub time_budget: Option<Duration>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub ply: Ply,
+    pub score: Score,
+    /// How many plies deep the last completed iteration went. If this is zero
+    /// then the time ran out before any iteration completed.
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+/// Rough categories of how good a ply looks one ply ahead, best first. These
+/// are only used to decide which plies to search first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Winning,
+    UndefendedCapture,
+    Capture,
+    /// the opponent cannot capture anything afterwards
+    Safe,
+    /// the opponent cannot win immediately afterwards
+    Viable,
+    Losing,
+}
+
+pub fn priority(position: &Position, ply: Ply) -> Priority {
+    let us = position.to_move;
+    let them = us.opponent();
+
+    let mut next = *position;
+    next.apply(ply);
+
+    if next.outcome() == Some(us) {
+        return Priority::Winning;
+    }
+
+    let their_attacks = next.attacks(them);
+    let their_master = next.bitboard.master(them);
+
+    let they_can_win = their_attacks & next.bitboard.master(us) != 0 ||
+        next.reach(them, their_master) & (1 << goal_index(them)) != 0;
+
+    if they_can_win {
+        return Priority::Losing;
+    }
+
+    let captured = next.bitboard.occupied(them) != position.bitboard.occupied(them);
+    let they_can_capture = their_attacks & next.bitboard.occupied(us) != 0;
+
+    match (captured, they_can_capture) {
+        (true, false) => Priority::UndefendedCapture,
+        (true, true) => Priority::Capture,
+        (false, false) => Priority::Safe,
+        (false, true) => Priority::Viable,
+    }
+}
+
+/// Puts `first` at the front if it's there, then the rest by `priority`.
+/// Working out a priority means applying the ply, so each is only worked out
+/// once rather than on every comparison.
+fn order_plies(position: &Position, plies: &mut [Ply], first: Option<Ply>) {
+    plies.sort_by_cached_key(|&ply| (Some(ply) != first, priority(position, ply)));
+}
+
+/// A static guess at how good the position is for the side to move.
+pub fn evaluate(position: &Position, weights: &Weights) -> Score {
+    let us = position.to_move;
+
+    side_score(position, us, weights) - side_score(position, us.opponent(), weights)
+}
+
+fn side_score(position: &Position, colour: PieceColour, weights: &Weights) -> Score {
+    let own = position.bitboard.occupied(colour);
+    let master = position.bitboard.master(colour);
+
+    let mut score = (own & !master).count_ones() as Score * weights.student;
+
+    if master != 0 {
+        if position.attacks(colour.opponent()) & master != 0 {
+            score -= weights.master_safety;
+        }
+
+        let master_index = master.trailing_zeros() as usize;
+        score += (4 - distance(master_index, goal_index(colour))) * weights.pagoda_distance;
+    }
+
+    let cards = position.cards(colour);
+    for &card in [cards.0, cards.1].iter() {
+        let mut mobility = 0;
+
+        let mut sources = own;
+        while sources != 0 {
+            let source_index = sources.trailing_zeros() as usize;
+            sources &= sources - 1;
+
+            mobility += (move_mask(card, colour, source_index) & !own).count_ones();
+        }
+
+        score += mobility as Score * weights.mobility;
+    }
+
+    score
+}
+
+/// The number of king moves between two squares.
+fn distance(a: usize, b: usize) -> Score {
+    let dx = (a % 5) as Score - (b % 5) as Score;
+    let dy = (a / 5) as Score - (b / 5) as Score;
+
+    std::cmp::max(dx.abs(), dy.abs())
+}
+
+/// Returns `None` if the game is already over.
+pub fn search(game: &GameState, limits: &Limits, weights: &Weights) -> Option<SearchResult> {
+    search_with_table(game, limits, weights, &mut TranspositionTable::default())
+}
+
+/// Like `search` but uses and updates the given table, so what was learned can
+/// be kept between moves.
+pub fn search_with_table(
+    game: &GameState,
+    limits: &Limits,
+    weights: &Weights,
+    table: &mut TranspositionTable,
+) -> Option<SearchResult> {
+    let position = Position::from(game);
+
+    let mut root_plies = position.legal_plies();
+    if root_plies.is_empty() {
+        return None;
+    }
+    let table_ply = table.probe(position.hash).and_then(|entry| entry.best);
+    order_plies(&position, &mut root_plies, table_ply);
+
+    let mut searcher = Searcher {
+        weights: *weights,
+        deadline: limits.time_budget.map(|budget| Instant::now() + budget),
+        nodes: 0,
+        aborted: false,
+        table,
+    };
+
+    let mut result = SearchResult {
+        ply: root_plies[0],
+        score: 0,
+        depth: 0,
+        nodes: 0,
+    };
+
+    for depth in 1..=limits.max_depth {
+        let mut alpha = -INFINITY;
+        let mut best_index = 0;
+
+        for (i, &ply) in root_plies.iter().enumerate() {
+            let mut next = position;
+            next.apply(ply);
+
+            let score = -searcher.negamax(&next, depth - 1, 1, -INFINITY, -alpha);
+
+            if searcher.aborted {
+                break;
+            }
+
+            if score > alpha {
+                alpha = score;
+                best_index = i;
+            }
+        }
+
+        if searcher.aborted {
+            //a partial iteration might have missed the best reply entirely
+            break;
+        }
+
+        result = SearchResult {
+            ply: root_plies[best_index],
+            score: alpha,
+            depth,
+            nodes: searcher.nodes,
+        };
+
+        //search the best ply first next time around
+        root_plies[..=best_index].rotate_right(1);
+
+        searcher.table.store(Entry {
+            hash: position.hash,
+            depth,
+            bound: Bound::Exact,
+            score: alpha,
+            best: Some(result.ply),
+        });
+
+        if alpha.abs() >= WIN_THRESHOLD {
+            break;
+        }
+    }
+
+    result.nodes = searcher.nodes;
+
+    Some(result)
+}
+
+struct Searcher<'a> {
+    weights: Weights,
+    deadline: Option<Instant>,
+    nodes: u64,
+    aborted: bool,
+    table: &'a mut TranspositionTable,
+}
+
+/// Win scores depend on how far from the root they were found, so they are
+/// stored relative to the position itself instead.
+fn score_to_table(score: Score, ply_count: u32) -> Score {
+    if score >= WIN_THRESHOLD {
+        score + ply_count as Score
+    } else if score <= -WIN_THRESHOLD {
+        score - ply_count as Score
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: Score, ply_count: u32) -> Score {
+    if score >= WIN_THRESHOLD {
+        score - ply_count as Score
+    } else if score <= -WIN_THRESHOLD {
+        score + ply_count as Score
+    } else {
+        score
+    }
+}
+
+impl<'a> Searcher<'a> {
+    fn negamax(
+        &mut self,
+        position: &Position,
+        depth: u32,
+        ply_count: u32,
+        mut alpha: Score,
+        beta: Score,
+    ) -> Score {
+        self.nodes += 1;
+
+        if self.nodes & 1023 == 0 {
+            if let Some(deadline) = self.deadline {
+                self.aborted = Instant::now() >= deadline;
+            }
+        }
+
+        if self.aborted {
+            return 0;
+        }
+
+        if let Some(winner) = position.outcome() {
+            let score = WIN_SCORE - ply_count as Score;
+
+            return if winner == position.to_move {
+                score
+            } else {
+                -score
+            };
+        }
+
+        if depth == 0 {
+            return evaluate(position, &self.weights);
+        }
+
+        let original_alpha = alpha;
+        let mut beta = beta;
+        let mut table_ply = None;
+
+        if let Some(entry) = self.table.probe(position.hash) {
+            table_ply = entry.best;
+
+            if entry.depth >= depth {
+                let score = score_from_table(entry.score, ply_count);
+
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower => alpha = std::cmp::max(alpha, score),
+                    Bound::Upper => beta = std::cmp::min(beta, score),
+                }
+
+                if alpha >= beta {
+                    return score;
+                }
+            }
+        }
+
+        let mut plies = position.legal_plies();
+        order_plies(position, &mut plies, table_ply);
+
+        let mut best = -INFINITY;
+        let mut best_ply = None;
+
+        for &ply in plies.iter() {
+            let mut next = *position;
+            next.apply(ply);
+
+            let score = -self.negamax(&next, depth - 1, ply_count + 1, -beta, -alpha);
+
+            if score > best {
+                best = score;
+                best_ply = Some(ply);
+            }
+            if best > alpha {
+                alpha = best;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if !self.aborted {
+            let bound = if best <= original_alpha {
+                Bound::Upper
+            } else if best >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+
+            self.table.store(Entry {
+                hash: position.hash,
+                depth,
+                bound,
+                score: score_to_table(best, ply_count),
+                best: best_ply,
+            });
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod searching {
+    use ::*;
+    use fixtures::lone_masters;
+    use search::*;
+
+    const LIMITS: Limits = Limits {
+        max_depth: 4,
+        time_budget: None,
+    };
+
+    #[test]
+    fn takes_an_immediate_win() {
+        let game = lone_masters(7, 20);
+
+        let result = search(&game, &LIMITS, &Weights::default()).unwrap();
+
+        assert_eq!(
+            result.ply,
+            Ply::Move(
+                Move {
+                    source_index: 7,
+                    target_index: TOP_PAGODA_INDEX,
+                },
+                First,
+            )
+        );
+        assert!(result.score >= WIN_THRESHOLD);
+    }
+
+    #[test]
+    fn avoids_an_immediate_loss() {
+        //Red's Tiger can jump two squares forward onto the blue master, and
+        //Crab can step onto the square in front of Red's master
+        let game = lone_masters(12, TOP_PAGODA_INDEX);
+
+        let result = search(&game, &LIMITS, &Weights::default()).unwrap();
+
+        let mut next = game;
+        next.apply(result.ply);
+
+        assert!(next.legal_plies().iter().all(|&ply| {
+            let mut after = next;
+            after.apply(ply);
+            after.outcome() != Some(Red)
+        }));
+    }
+
+    #[test]
+    fn passes_when_it_cannot_move() {
+        let mut game = lone_masters(24, 0);
+        for &i in [4, 9, 14, 19].iter() {
+            game.board[i] = Some(BlueStudent);
+        }
+        game.blue_cards = (Tiger, Tiger);
+
+        let result = search(&game, &LIMITS, &Weights::default()).unwrap();
+
+        match result.ply {
+            Ply::Pass(_) => {}
+            Ply::Move(_, _) | Ply::Wind(_, _) => panic!("{:?} is not a pass", result.ply),
+        }
+    }
+
+    #[test]
+    fn always_returns_a_ply_without_time() {
+        let game = lone_masters(22, 2);
+
+        let limits = Limits {
+            max_depth: 20,
+            time_budget: Some(std::time::Duration::from_millis(0)),
+        };
+
+        assert!(search(&game, &limits, &Weights::default()).is_some());
+        assert!(search(&lone_masters(2, 7), &limits, &Weights::default()).is_none());
+    }
+
+    #[test]
+    fn weights_are_read_as_changes_to_the_defaults() {
+        let weights: Weights = "student=120, master_safety=0".parse().unwrap();
+
+        assert_eq!(
+            weights,
+            Weights {
+                student: 120,
+                master_safety: 0,
+                ..Weights::default()
+            }
+        );
+        assert_eq!(weights.to_string().parse(), Ok(weights));
+        assert!("student".parse::<Weights>().is_err());
+        assert!("bishop=3".parse::<Weights>().is_err());
+        assert!("student=2147483647".parse::<Weights>().is_err());
+        assert!("mobility=-1".parse::<Weights>().is_err());
+        assert!(format!("student={}", MAX_WEIGHT).parse::<Weights>().is_ok());
+    }
+}
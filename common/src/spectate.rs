@@ -0,0 +1,121 @@
+//! Pacing a game between two CPU players so it can be watched.
+
+use std::cmp;
+use std::time::{Duration, Instant};
+
+/// How long to wait between plies at each speed, slowest first. Thinking time
+/// comes on top of this, so the fastest speed is as fast as the CPUs can go.
+pub const DELAYS: [Duration; 8] = [
+    Duration::from_millis(3000),
+    Duration::from_millis(2000),
+    Duration::from_millis(1500),
+    Duration::from_millis(1000),
+    Duration::from_millis(600),
+    Duration::from_millis(300),
+    Duration::from_millis(100),
+    Duration::from_millis(0),
+];
+
+#[derive(Clone, Debug)]
+pub struct Spectator {
+    pub paused: bool,
+    /// An index into `DELAYS`.
+    pub speed: usize,
+    /// Set by `step` so one ply gets played while paused.
+    step: bool,
+    last_ply: Option<Instant>,
+}
+
+impl Default for Spectator {
+    fn default() -> Self {
+        Spectator {
+            paused: false,
+            speed: 3,
+            step: false,
+            last_ply: None,
+        }
+    }
+}
+
+impl Spectator {
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.step = false;
+    }
+
+    /// Pauses if needed and lets exactly one more ply be played.
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.step = true;
+    }
+
+    pub fn set_speed(&mut self, speed: usize) {
+        self.speed = cmp::min(speed, DELAYS.len() - 1);
+    }
+
+    pub fn delay(&self) -> Duration {
+        DELAYS[self.speed]
+    }
+
+    /// Call this each frame a CPU could play. Returns true if it should play
+    /// now, and counts that as the latest ply.
+    pub fn ready(&mut self, now: Instant) -> bool {
+        let ready = if self.paused {
+            self.step
+        } else {
+            match self.last_ply {
+                Some(last_ply) => now.duration_since(last_ply) >= self.delay(),
+                None => true,
+            }
+        };
+
+        if ready {
+            self.step = false;
+            self.last_ply = Some(now);
+        }
+
+        ready
+    }
+}
+
+#[cfg(test)]
+mod pacing {
+    use spectate::*;
+
+    #[test]
+    fn waits_for_the_delay_between_plies() {
+        let mut spectator = Spectator::default();
+        spectator.set_speed(0);
+        let start = Instant::now();
+
+        assert!(spectator.ready(start));
+        assert!(!spectator.ready(start + Duration::from_millis(100)));
+        assert!(spectator.ready(start + DELAYS[0]));
+    }
+
+    #[test]
+    fn pausing_only_plays_when_stepped() {
+        let mut spectator = Spectator::default();
+        let start = Instant::now();
+        let later = start + Duration::from_secs(60);
+
+        spectator.toggle_pause();
+        assert!(!spectator.ready(later));
+
+        spectator.step();
+        assert!(spectator.ready(later));
+        assert!(!spectator.ready(later));
+        assert!(spectator.paused);
+
+        spectator.toggle_pause();
+        assert!(spectator.ready(later + DELAYS[spectator.speed]));
+    }
+
+    #[test]
+    fn speeds_stop_at_the_fastest() {
+        let mut spectator = Spectator::default();
+        spectator.set_speed(100);
+
+        assert_eq!(spectator.delay(), Duration::from_millis(0));
+    }
+}
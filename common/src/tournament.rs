@@ -0,0 +1,437 @@
+//! Playing CPU players against each other without the UI, to measure how
+//! strong they are. Games are dealt from seeds the same way new games in the
+//! UI are, so any game that comes up here can be looked at there.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::{Rng, StdRng};
+
+use ::*;
+use cpu::{pass_ply, Agent, Cpu, CpuPlayer, Tuned};
+
+/// How many standard errors either side of the mean cover 95% of a normal
+/// distribution.
+const Z_95: f64 = 1.96;
+
+/// Plays a random legal ply, or the pass the CPU players would choose. This is
+/// the weakest player there is, which makes it a useful floor to measure the
+/// others against.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RandomPlayer;
+
+impl Agent for RandomPlayer {
+    fn choose_ply(&mut self, game: &GameState, rng: &mut StdRng) -> Option<Ply> {
+        if game.must_pass() {
+            return pass_ply(game);
+        }
+
+        rng.choose(&game.legal_plies()).cloned()
+    }
+}
+
+impl fmt::Display for RandomPlayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Random")
+    }
+}
+
+/// A player along with the name to report its results under.
+pub struct Entrant {
+    pub name: String,
+    pub agent: Box<dyn Agent>,
+}
+
+impl Entrant {
+    pub fn new<A: Agent + fmt::Display + 'static>(agent: A) -> Self {
+        Entrant {
+            name: agent.to_string(),
+            agent: Box::new(agent),
+        }
+    }
+}
+
+impl FromStr for Entrant {
+    type Err = String;
+
+    /// Reads "random" or anything `CpuPlayer` or `Tuned` can be read from.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("random") {
+            Ok(Entrant::new(RandomPlayer))
+        } else if s.contains(':') {
+            s.parse::<Tuned>().map(|tuned| Entrant::new(Cpu::from(tuned)))
+        } else {
+            s.parse::<CpuPlayer>().map(|player| Entrant::new(Cpu::new(player)))
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MatchSettings {
+    /// Odd numbers of games leave the last deal played from one side only.
+    pub games: usize,
+    /// Each pair of games uses the next seed up from this one.
+    pub first_seed: usize,
+    pub deck: Deck,
+    /// Games that go on longer than this are counted as draws.
+    pub max_plies: usize,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        MatchSettings {
+            games: 100,
+            first_seed: 0,
+            deck: Deck::Base,
+            max_plies: 200,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GameOutcome {
+    pub seed: usize,
+    /// The colour the first player in the match had.
+    pub first_colour: PieceColour,
+    /// `None` if the game was a draw.
+    pub winner: Option<PieceColour>,
+    pub plies: usize,
+}
+
+/// Deals the game `seed` gives and plays it out. Returns the winner, or `None`
+/// if nobody had won after `max_plies` plies, along with how many plies were
+/// played.
+pub fn play_game(
+    blue: &mut dyn Agent,
+    red: &mut dyn Agent,
+    seed: usize,
+    deck: Deck,
+    max_plies: usize,
+) -> (Option<PieceColour>, usize) {
+    let mut rng = seeded_rng(seed);
+    let mut game = GameState::deal_from(&mut rng, deck);
+    let mut plies = 0;
+
+    while plies < max_plies && game.outcome().is_none() {
+        let agent: &mut dyn Agent = match game.to_move {
+            Blue => &mut *blue,
+            Red => &mut *red,
+        };
+
+        match agent.choose_ply(&game, &mut rng) {
+            Some(ply) => game.apply(ply),
+            None => break,
+        }
+
+        plies += 1;
+    }
+
+    (game.outcome(), plies)
+}
+
+/// Wins, losses and draws from one player's point of view.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Score {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl Score {
+    pub fn record(&mut self, colour: PieceColour, winner: Option<PieceColour>) {
+        match winner {
+            Some(winner) if winner == colour => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    /// A win is worth one point and a draw half of one.
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+
+    /// The points per game, or a half if no games have been played.
+    pub fn rate(&self) -> f64 {
+        if self.games() == 0 {
+            0.5
+        } else {
+            self.points() / self.games() as f64
+        }
+    }
+
+    /// A 95% confidence interval for `rate`. This is the Wilson score interval,
+    /// which unlike the usual normal approximation still gives a sensible range
+    /// when every game went the same way. It treats draws as though they were
+    /// as uncertain as a coin toss, so it errs on the wide side when there are
+    /// many of them.
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        let games = self.games() as f64;
+        if games == 0.0 {
+            return (0.0, 1.0);
+        }
+
+        let rate = self.rate();
+        let z_squared = Z_95 * Z_95;
+        let scale = 1.0 + z_squared / games;
+
+        let center = (rate + z_squared / (2.0 * games)) / scale;
+        let margin = Z_95 / scale
+            * (rate * (1.0 - rate) / games + z_squared / (4.0 * games * games)).sqrt();
+
+        ((center - margin).max(0.0), (center + margin).min(1.0))
+    }
+
+    pub fn flipped(&self) -> Score {
+        Score {
+            wins: self.losses,
+            losses: self.wins,
+            draws: self.draws,
+        }
+    }
+}
+
+impl std::ops::Add for Score {
+    type Output = Score;
+
+    fn add(self, other: Score) -> Score {
+        Score {
+            wins: self.wins + other.wins,
+            losses: self.losses + other.losses,
+            draws: self.draws + other.draws,
+        }
+    }
+}
+
+/// The results of a match, from the first player's point of view.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchReport {
+    pub names: (String, String),
+    pub as_blue: Score,
+    pub as_red: Score,
+    pub games: Vec<GameOutcome>,
+}
+
+impl MatchReport {
+    pub fn total(&self) -> Score {
+        self.as_blue + self.as_red
+    }
+}
+
+impl fmt::Display for MatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} vs {}, {} games",
+            self.names.0,
+            self.names.1,
+            self.games.len()
+        )?;
+        writeln!(
+            f,
+            "{:<10}{:>6}{:>8}{:>7}{:>8}   95% CI",
+            "", "Wins", "Losses", "Draws", "Score"
+        )?;
+
+        for &(label, score) in [
+            ("As Blue", self.as_blue),
+            ("As Red", self.as_red),
+            ("Total", self.total()),
+        ].iter()
+        {
+            let (low, high) = score.confidence_interval();
+
+            writeln!(
+                f,
+                "{:<10}{:>6}{:>8}{:>7}{:>7.1}%   {:.1}% to {:.1}%",
+                label,
+                score.wins,
+                score.losses,
+                score.draws,
+                score.rate() * 100.0,
+                low * 100.0,
+                high * 100.0
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Plays `settings.games` games between `a` and `b`. Each deal is played twice
+/// in a row with the sides swapped, so neither player gets luckier cards.
+/// `progress` is called after each game.
+pub fn run_match<F>(
+    a: &mut Entrant,
+    b: &mut Entrant,
+    settings: &MatchSettings,
+    mut progress: F,
+) -> MatchReport
+where
+    F: FnMut(&GameOutcome),
+{
+    let mut report = MatchReport {
+        names: (a.name.clone(), b.name.clone()),
+        as_blue: Score::default(),
+        as_red: Score::default(),
+        games: Vec::with_capacity(settings.games),
+    };
+
+    for game in 0..settings.games {
+        let seed = settings.first_seed.wrapping_add(game / 2);
+        let first_colour = if game % 2 == 0 { Blue } else { Red };
+
+        let (blue, red): (&mut dyn Agent, &mut dyn Agent) = match first_colour {
+            Blue => (&mut *a.agent, &mut *b.agent),
+            Red => (&mut *b.agent, &mut *a.agent),
+        };
+        let (winner, plies) = play_game(blue, red, seed, settings.deck, settings.max_plies);
+
+        match first_colour {
+            Blue => report.as_blue.record(Blue, winner),
+            Red => report.as_red.record(Red, winner),
+        }
+
+        let outcome = GameOutcome {
+            seed,
+            first_colour,
+            winner,
+            plies,
+        };
+        progress(&outcome);
+        report.games.push(outcome);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod matches {
+    use tournament::*;
+    use cpu::{Difficulty, Engine};
+
+    fn settings(games: usize) -> MatchSettings {
+        MatchSettings {
+            games,
+            first_seed: 42,
+            ..MatchSettings::default()
+        }
+    }
+
+    #[test]
+    fn sides_alternate_on_the_same_deal() {
+        let report = run_match(
+            &mut Entrant::new(RandomPlayer),
+            &mut Entrant::new(RandomPlayer),
+            &settings(4),
+            |_| {},
+        );
+
+        let seeds: Vec<usize> = report.games.iter().map(|g| g.seed).collect();
+        let colours: Vec<PieceColour> = report.games.iter().map(|g| g.first_colour).collect();
+
+        assert_eq!(seeds, vec![42, 42, 43, 43]);
+        assert_eq!(colours, vec![Blue, Red, Blue, Red]);
+        assert_eq!(report.as_blue.games(), 2);
+        assert_eq!(report.as_red.games(), 2);
+    }
+
+    #[test]
+    fn the_same_seeds_give_the_same_results() {
+        let play = || {
+            run_match(
+                &mut Entrant::new(RandomPlayer),
+                &mut Entrant::new(RandomPlayer),
+                &settings(6),
+                |_| {},
+            ).games
+        };
+
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn games_that_go_on_too_long_are_draws() {
+        let (winner, plies) = play_game(&mut RandomPlayer, &mut RandomPlayer, 42, Deck::Base, 0);
+
+        assert_eq!(winner, None);
+        assert_eq!(plies, 0);
+    }
+
+    #[test]
+    fn the_casual_cpu_beats_random_play() {
+        let mut casual = Entrant::new(Cpu::new(CpuPlayer {
+            engine: Engine::Minimax,
+            difficulty: Difficulty::Casual,
+        }));
+
+        let report = run_match(&mut casual, &mut Entrant::new(RandomPlayer), &settings(10), |_| {});
+
+        assert!(report.total().confidence_interval().0 > 0.5, "{}", report);
+    }
+
+    #[test]
+    fn scores_are_points_per_game() {
+        let score = Score {
+            wins: 3,
+            losses: 1,
+            draws: 2,
+        };
+
+        assert_eq!(score.games(), 6);
+        assert_eq!(score.points(), 4.0);
+        assert_eq!(score.flipped().points(), 2.0);
+        assert_eq!(Score::default().rate(), 0.5);
+    }
+
+    #[test]
+    fn confidence_intervals_narrow_with_more_games() {
+        let few = Score {
+            wins: 6,
+            losses: 4,
+            draws: 0,
+        };
+        let many = Score {
+            wins: 600,
+            losses: 400,
+            draws: 0,
+        };
+
+        let (few_low, few_high) = few.confidence_interval();
+        let (many_low, many_high) = many.confidence_interval();
+
+        assert!(few_low < many_low && many_low < 0.6);
+        assert!(few_high > many_high && many_high > 0.6);
+    }
+
+    #[test]
+    fn confidence_intervals_stay_open_after_a_shutout() {
+        let shutout = Score {
+            wins: 3,
+            losses: 0,
+            draws: 0,
+        };
+
+        let (low, high) = shutout.confidence_interval();
+        assert!(0.0 < low && low < 1.0);
+        assert!((high - 1.0).abs() < 1e-9);
+
+        let (low, high) = shutout.flipped().confidence_interval();
+        assert!(low.abs() < 1e-9);
+        assert!(0.0 < high && high < 1.0);
+    }
+
+    #[test]
+    fn entrants_are_read_by_name() {
+        assert_eq!("random".parse::<Entrant>().map(|e| e.name), Ok("Random".to_string()));
+        assert_eq!(
+            "strong-mcts".parse::<Entrant>().map(|e| e.name),
+            Ok("Strong MCTS".to_string())
+        );
+        assert!("strong".parse::<Entrant>().is_err());
+    }
+}
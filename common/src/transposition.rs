@@ -0,0 +1,123 @@
+//! A fixed-size table remembering what earlier searches found out about
+//! positions, keyed by Zobrist hash.
+
+use ::*;
+use search::Score;
+use zobrist::Hash;
+
+/// How the stored score relates to the real score of the position.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Bound {
+    Exact,
+    /// The real score is at least this much.
+    Lower,
+    /// The real score is at most this much.
+    Upper,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub hash: Hash,
+    pub depth: u32,
+    pub bound: Bound,
+    pub score: Score,
+    pub best: Option<Ply>,
+}
+
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+}
+
+impl TranspositionTable {
+    /// `size` is rounded up to the next power of two.
+    pub fn new(size: usize) -> Self {
+        TranspositionTable {
+            entries: vec![None; size.max(1).next_power_of_two()],
+        }
+    }
+
+    fn slot(&self, hash: Hash) -> usize {
+        (hash as usize) & (self.entries.len() - 1)
+    }
+
+    pub fn probe(&self, hash: Hash) -> Option<&Entry> {
+        self.entries[self.slot(hash)]
+            .as_ref()
+            .filter(|entry| entry.hash == hash)
+    }
+
+    /// Keeps an entry for the same position from a deeper search rather than
+    /// replacing it, otherwise always replaces.
+    pub fn store(&mut self, entry: Entry) {
+        let slot = self.slot(entry.hash);
+
+        let keep_old = match self.entries[slot] {
+            Some(old) => old.hash == entry.hash && old.depth > entry.depth,
+            None => false,
+        };
+
+        if !keep_old {
+            self.entries[slot] = Some(entry);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for entry in self.entries.iter_mut() {
+            *entry = None;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(1 << 16)
+    }
+}
+
+#[cfg(test)]
+mod table {
+    use transposition::*;
+
+    fn entry(hash: Hash, depth: u32) -> Entry {
+        Entry {
+            hash,
+            depth,
+            bound: Bound::Exact,
+            score: depth as Score,
+            best: None,
+        }
+    }
+
+    #[test]
+    fn stores_and_probes_by_full_hash() {
+        let mut table = TranspositionTable::new(5);
+        assert_eq!(table.len(), 8);
+
+        table.store(entry(3, 1));
+
+        assert_eq!(table.probe(3), Some(&entry(3, 1)));
+        //same slot, different position
+        assert_eq!(table.probe(11), None);
+    }
+
+    #[test]
+    fn prefers_deeper_entries_for_the_same_position() {
+        let mut table = TranspositionTable::new(8);
+
+        table.store(entry(3, 4));
+        table.store(entry(3, 2));
+        assert_eq!(table.probe(3).map(|e| e.depth), Some(4));
+
+        table.store(entry(11, 1));
+        assert_eq!(table.probe(3), None);
+        assert_eq!(table.probe(11).map(|e| e.depth), Some(1));
+    }
+}
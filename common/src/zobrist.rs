@@ -0,0 +1,171 @@
+//! Zobrist hashing of positions. The keys are generated at compile time from a
+//! fixed seed so hashes stay the same between runs and builds.
+//!
+//! Which slot a card sits in within a hand does not change the hash, since it
+//! doesn't change which moves are possible.
+
+use ::*;
+
+pub type Hash = u64;
+
+struct Keys {
+    pieces: [[Hash; 25]; 5],
+    /// indexed by `holder_index`
+    cards: [[Hash; CARD_COUNT + MAX_CUSTOM_CARDS]; 3],
+    red_to_move: Hash,
+}
+
+const KEYS: Keys = build_keys();
+
+const fn splitmix64(state: u64) -> (u64, Hash) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+    (state, z ^ (z >> 31))
+}
+
+const fn build_keys() -> Keys {
+    let mut keys = Keys {
+        pieces: [[0; 25]; 5],
+        cards: [[0; CARD_COUNT + MAX_CUSTOM_CARDS]; 3],
+        red_to_move: 0,
+    };
+
+    let mut state = 0x006F_6E69_7461_6D61; //"onitama"
+
+    let mut piece = 0;
+    while piece < 5 {
+        let mut square = 0;
+        while square < 25 {
+            let (next_state, key) = splitmix64(state);
+            state = next_state;
+            keys.pieces[piece][square] = key;
+
+            square += 1;
+        }
+
+        piece += 1;
+    }
+
+    let mut holder = 0;
+    while holder < 3 {
+        let mut card = 0;
+        while card < CARD_COUNT + MAX_CUSTOM_CARDS {
+            let (next_state, key) = splitmix64(state);
+            state = next_state;
+            keys.cards[holder][card] = key;
+
+            card += 1;
+        }
+
+        holder += 1;
+    }
+
+    keys.red_to_move = splitmix64(state).1;
+
+    keys
+}
+
+/// `None` means the center card.
+fn holder_index(holder: Option<PieceColour>) -> usize {
+    match holder {
+        Some(Red) => 0,
+        Some(Blue) => 1,
+        None => 2,
+    }
+}
+
+pub fn piece_key(piece: Piece, index: usize) -> Hash {
+    KEYS.pieces[piece as usize][index]
+}
+
+/// `holder` is `None` for the center card.
+pub fn card_key(card: Card, holder: Option<PieceColour>) -> Hash {
+    KEYS.cards[holder_index(holder)][card.index()]
+}
+
+pub fn to_move_key(colour: PieceColour) -> Hash {
+    match colour {
+        Red => KEYS.red_to_move,
+        Blue => 0,
+    }
+}
+
+pub fn hash(game: &GameState) -> Hash {
+    let mut result = to_move_key(game.to_move);
+
+    for (index, square) in game.board.iter().enumerate() {
+        if let Some(piece) = *square {
+            result ^= piece_key(piece, index);
+        }
+    }
+
+    for &colour in [Red, Blue].iter() {
+        let cards = game.cards(colour);
+        result ^= card_key(cards.0, Some(colour)) ^ card_key(cards.1, Some(colour));
+    }
+
+    result ^ card_key(game.center_card, None)
+}
+
+impl GameState {
+    pub fn hash(&self) -> Hash {
+        hash(self)
+    }
+}
+
+#[cfg(test)]
+mod hashing {
+    use bitboard::Position;
+    use rand::{Rng, SeedableRng};
+    use zobrist::*;
+
+    #[test]
+    fn keys_are_stable() {
+        //if this changes then saved hashes from earlier versions won't match
+        assert_eq!(piece_key(RedStudent, 0), 0x074D_4F64_5C6C_22AB);
+    }
+
+    #[test]
+    fn slot_order_within_a_hand_does_not_matter() {
+        let seed: &[_] = &[42];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let game = GameState::deal(&mut rng);
+
+        let mut swapped = game;
+        swapped.blue_cards = (game.blue_cards.1, game.blue_cards.0);
+
+        assert_eq!(game.hash(), swapped.hash());
+
+        let mut other_side = game;
+        other_side.to_move = game.to_move.opponent();
+
+        assert_ne!(game.hash(), other_side.hash());
+    }
+
+    quickcheck! {
+        fn incremental_hash_matches_full_hash(seed: usize) -> bool {
+            let seed: &[_] = &[seed];
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+            let mut position = Position::from(&GameState::deal(&mut rng));
+
+            for _ in 0..200 {
+                if position.hash != position.to_game_state().hash() {
+                    return false;
+                }
+
+                let plies = position.legal_plies();
+                match rng.choose(&plies) {
+                    Some(&ply) => position.apply(ply),
+                    None => break,
+                }
+            }
+
+            true
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Custom cards can only be installed once per process, so these live in their
+//! own test binary where installing them can't change what the unit tests see.
+
+extern crate common;
+extern crate rand;
+
+use common::*;
+use common::Card::*;
+use common::PieceColour::*;
+use common::bitboard::Position;
+use common::card_file;
+use common::cpu::{Agent, Cpu, CpuPlayer, Difficulty, Engine};
+use common::perft::{perft, perft_position};
+
+use rand::{SeedableRng, StdRng};
+
+use std::sync::Once;
+
+const CARD_FILE: &str = "
+# name | stamp | offsets
+Wolf | red | (0, -2) (-1, 1) (1, 1)
+Snow Owl | blue | (-2, -2) (2, -2)
+";
+
+static INSTALL: Once = Once::new();
+
+fn install() {
+    INSTALL.call_once(|| {
+        card_file::install(card_file::parse(CARD_FILE).unwrap()).unwrap();
+    });
+}
+
+fn game() -> GameState {
+    install();
+
+    GameState {
+        board: starting_board(),
+        blue_cards: (Custom(1), Crab),
+        red_cards: (Custom(0), Frog),
+        center_card: Rabbit,
+        to_move: Blue,
+    }
+}
+
+#[test]
+fn custom_cards_are_known_by_name() {
+    install();
+
+    assert_eq!("snow owl".parse(), Ok(Custom(1)));
+    assert_eq!(Custom(0).name(), "Wolf");
+    assert_eq!(Custom(0).stamp(), Red);
+    assert!(Card::all_values().contains(&Custom(1)));
+    assert!(Deck::Mixed.cards().contains(&Custom(0)));
+    assert!(!Deck::Base.cards().contains(&Custom(0)));
+}
+
+#[test]
+fn installing_twice_is_an_error() {
+    install();
+
+    assert!(card_file::install(Vec::new()).is_err());
+}
+
+#[test]
+fn installing_too_many_cards_is_an_error() {
+    let wolf = card_file::parse(CARD_FILE).unwrap().remove(0);
+    let definitions = vec![wolf; MAX_CUSTOM_CARDS + 1];
+
+    let error = card_file::install(definitions).unwrap_err();
+
+    assert!(error.message.contains("at most"), "{}", error);
+}
+
+#[test]
+fn custom_cards_move_as_defined() {
+    let game = game();
+
+    //Snow Owl jumps two squares diagonally forwards each way, from c5 to a3
+    //and e3.
+    let targets = valid_move_locations(&game.board, &Custom(1), 22, Blue);
+    assert_eq!(targets, vec![(0, 2), (4, 2)]);
+
+    //Red sees Wolf from the other side, so it jumps two squares towards Blue.
+    let targets = valid_move_locations(&game.board, &Custom(0), 2, Red);
+    assert!(targets.contains(&(2, 2)));
+}
+
+#[test]
+fn bitboard_moves_match_for_custom_cards() {
+    let game = game();
+
+    for depth in 1..5 {
+        assert_eq!(perft_position(&Position::from(&game), depth), perft(&game, depth));
+    }
+}
+
+#[test]
+fn positions_with_custom_cards_round_trip() {
+    let game = game();
+
+    assert_eq!(game.to_string().parse(), Ok(game));
+}
+
+#[test]
+fn every_cpu_can_play_custom_cards() {
+    let game = game();
+    let legal = game.legal_plies();
+
+    for difficulty in Difficulty::all_values() {
+        for engine in Engine::all_values() {
+            let seed: &[_] = &[42];
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+            let mut cpu = Cpu::new(CpuPlayer { engine, difficulty });
+
+            let ply = cpu.choose_ply(&game, &mut rng).unwrap();
+
+            assert!(legal.contains(&ply), "{} chose {:?}", cpu, ply);
+        }
+    }
+}
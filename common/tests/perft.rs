@@ -0,0 +1,110 @@
+//! Known perft counts from the starting position with fixed cards. If move
+//! generation changes, for example in `get_offsets` or in how Red's moves are
+//! flipped, these counts will change too.
+
+extern crate common;
+extern crate rand;
+
+use common::*;
+use common::Card::*;
+use common::Piece::*;
+use common::PieceColour::*;
+use common::bitboard::Position;
+use common::perft::{divide, perft, perft_position};
+
+use rand::{SeedableRng, StdRng};
+
+fn deal(blue_cards: (Card, Card), red_cards: (Card, Card), center_card: Card) -> GameState {
+    GameState {
+        board: starting_board(),
+        blue_cards,
+        red_cards,
+        center_card,
+        to_move: Blue,
+    }
+}
+
+/// Blue's cards, Red's cards, the center card and perft counts for depths 1 to 5.
+type KnownCounts = ((Card, Card), (Card, Card), Card, [u64; 5]);
+
+const KNOWN_COUNTS: [KnownCounts; 4] = [
+    ((Tiger, Crab), (Monkey, Frog), Rabbit, [10, 120, 1428, 16937, 227945]),
+    ((Dragon, Elephant), (Mantis, Boar), Goose, [14, 182, 2652, 40916, 626965]),
+    ((Horse, Eel), (Rooster, Ox), Cobra, [9, 81, 846, 10260, 142249]),
+    ((Frog, Rabbit), (Eel, Cobra), Crane, [8, 64, 640, 7280, 85686]),
+];
+
+#[test]
+fn known_counts() {
+    for &(blue_cards, red_cards, center_card, counts) in KNOWN_COUNTS.iter() {
+        let game = deal(blue_cards, red_cards, center_card);
+
+        for (i, &expected) in counts.iter().enumerate() {
+            let depth = i as u32 + 1;
+
+            assert_eq!(
+                perft(&game, depth),
+                expected,
+                "perft({}) with {:?} {:?} {:?}",
+                depth,
+                blue_cards,
+                red_cards,
+                center_card
+            );
+        }
+    }
+}
+
+#[test]
+fn bitboard_counts_match() {
+    for &(blue_cards, red_cards, center_card, counts) in KNOWN_COUNTS.iter() {
+        let game = deal(blue_cards, red_cards, center_card);
+
+        assert_eq!(perft_position(&Position::from(&game), 5), counts[4]);
+    }
+}
+
+#[test]
+fn divide_adds_up() {
+    let (blue_cards, red_cards, center_card, counts) = KNOWN_COUNTS[0];
+    let game = deal(blue_cards, red_cards, center_card);
+
+    let total: u64 = divide(&game, 3).iter().map(|&(_, count)| count).sum();
+
+    assert_eq!(total, counts[2]);
+}
+
+/// Turns the board around and swaps the colours, which should not change
+/// anything about how many moves there are.
+fn mirrored(game: &GameState) -> GameState {
+    let mut board = [None; 25];
+
+    for (index, square) in game.board.iter().enumerate() {
+        board[24 - index] = square.map(|piece| match piece {
+            RedStudent => BlueStudent,
+            BlueStudent => RedStudent,
+            RedMaster => BlueMaster,
+            BlueMaster => RedMaster,
+            WindSpirit => WindSpirit,
+        });
+    }
+
+    GameState {
+        board,
+        blue_cards: game.red_cards,
+        red_cards: game.blue_cards,
+        center_card: game.center_card,
+        to_move: game.to_move.opponent(),
+    }
+}
+
+#[test]
+fn red_and_blue_are_symmetric() {
+    for seed in 0..20 {
+        let seed: &[_] = &[seed];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let game = GameState::deal(&mut rng);
+
+        assert_eq!(perft(&game, 4), perft(&mirrored(&game), 4));
+    }
+}
@@ -0,0 +1,44 @@
+//! Checks that each difficulty still beats the one below it, so a change that
+//! makes the CPU weaker shows up as a failing test. These use the difficulties
+//! that do not depend on the clock, so the results only change when the play
+//! does. Use the `tournament` binary to measure the stronger ones.
+
+extern crate common;
+
+use common::cpu::{Cpu, CpuPlayer, Difficulty, Engine};
+use common::tournament::{run_match, Entrant, MatchSettings, RandomPlayer};
+
+fn cpu(difficulty: Difficulty, engine: Engine) -> Entrant {
+    Entrant::new(Cpu::new(CpuPlayer { engine, difficulty }))
+}
+
+fn assert_stronger(mut stronger: Entrant, mut weaker: Entrant) {
+    let settings = MatchSettings {
+        games: 20,
+        first_seed: 1,
+        ..MatchSettings::default()
+    };
+
+    let report = run_match(&mut stronger, &mut weaker, &settings, |_| {});
+    let (low, _) = report.total().confidence_interval();
+
+    assert!(low > 0.5, "\n{}", report);
+}
+
+#[test]
+fn beginner_minimax_beats_random_play() {
+    assert_stronger(cpu(Difficulty::Beginner, Engine::Minimax), Entrant::new(RandomPlayer));
+}
+
+#[test]
+fn beginner_mcts_beats_random_play() {
+    assert_stronger(cpu(Difficulty::Beginner, Engine::Mcts), Entrant::new(RandomPlayer));
+}
+
+#[test]
+fn casual_minimax_beats_beginner_minimax() {
+    assert_stronger(
+        cpu(Difficulty::Casual, Engine::Minimax),
+        cpu(Difficulty::Beginner, Engine::Minimax),
+    );
+}
@@ -91,7 +91,7 @@ impl Application {
 const TILE_SIZE: i32 = 64;
 
 fn main() {
-    terminal::open("____", 80, 40);
+    terminal::open("____", 104, 43);
     terminal::set(config::Window::empty().resizeable(true));
     terminal::set(vec![
         config::InputFilter::Group {
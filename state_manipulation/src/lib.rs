@@ -7,8 +7,20 @@ use common::PieceColour::*;
 use common::Card::*;
 use common::Turn::*;
 use common::PairIndex::*;
+use common::card_file;
+use common::cpu::{Cpu, CpuPlayer, Thinking};
+use common::editor::{CardSlot, Editor};
+use common::history::{History, Record};
+use common::notation::Notation;
+use common::record::{self, GameRecord};
+use common::replay::Replay;
+use common::save::{self, SavedGame};
+use common::spectate::{self, Spectator};
 
-use rand::{StdRng, SeedableRng, Rng};
+use rand::{Rng, StdRng};
+
+use std::path::PathBuf;
+use std::time::Instant;
 
 //NOTE(Ryan1729): debug_assertions only appears to work correctly when the
 //crate is not a dylib. Assuming you make this crate *not* a dylib on release,
@@ -19,10 +31,7 @@ pub fn new_state(size: Size) -> State {
     //skip the title screen
     println!("debug on");
 
-    let seed: &[_] = &[42];
-    let rng: StdRng = SeedableRng::from_seed(seed);
-
-    make_state(rng)
+    start_state(42)
 }
 #[cfg(not(debug_assertions))]
 #[no_mangle]
@@ -34,10 +43,206 @@ pub fn new_state(size: Size) -> State {
         .unwrap_or(42);
 
     println!("{}", timestamp);
-    let seed: &[_] = &[timestamp as usize];
-    let rng: StdRng = SeedableRng::from_seed(seed);
 
-    make_state(rng)
+    start_state(timestamp as usize)
+}
+
+const DEFAULT_SAVE_PATH: &str = "onitama.save";
+const RECORD_PATH: &str = "onitama.record";
+const POSITION_PATH: &str = "onitama.position";
+
+//the value after `flag` on the command line, like the path in `--load game.save`
+fn cli_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+
+    args.next();
+    args.next()
+}
+
+//`--deck base`, `--deck expansion`, `--deck mixed` or `--deck wind`, defaulting
+//to the base game's cards.
+fn deck_setting() -> Deck {
+    match cli_value("--deck").map(|deck| deck.parse()) {
+        Some(Ok(deck)) => deck,
+        Some(Err(e)) => {
+            println!("{}", e);
+            Deck::default()
+        }
+        None => Deck::default(),
+    }
+}
+
+//`--side blue`, `--side red` or `--side random` for which colour to play
+//against the CPU, defaulting to Blue.
+fn side_setting() -> Side {
+    match cli_value("--side").map(|side| side.parse()) {
+        Some(Ok(side)) => side,
+        Some(Err(e)) => {
+            println!("{}", e);
+            Side::default()
+        }
+        None => Side::default(),
+    }
+}
+
+fn save_path() -> PathBuf {
+    PathBuf::from(cli_value("--load").unwrap_or_else(|| DEFAULT_SAVE_PATH.to_string()))
+}
+
+//`--cards PATH` adds the custom cards defined in that file. This needs to run
+//again after a hot reload, since the reloaded code starts without any custom
+//cards. Returns what went wrong, if anything did.
+fn install_custom_cards() -> Option<String> {
+    if card_file::is_installed() {
+        return None;
+    }
+
+    let (definitions, problem) = match cli_value("--cards").map(PathBuf::from) {
+        Some(path) => {
+            match card_file::load(&path) {
+                Ok(definitions) => (definitions, None),
+                Err(e) => (Vec::new(), Some(format!("Could not load cards: {}", e))),
+            }
+        }
+        None => (Vec::new(), None),
+    };
+
+    //installing nothing still means this only runs once
+    card_file::install(definitions).err().map(|e| e.to_string()).or(problem)
+}
+
+fn start_state(seed: usize) -> State {
+    let problem = install_custom_cards();
+
+    let (mut state, from_save) = load_or_make_state(seed);
+
+    //a save already says who plays which side
+    if !from_save {
+        state.side = side_setting();
+        state.mode = Mode::VsCpu(state.side.colour(&mut state.rng));
+        state.turn = turn_for(&state.game, state.mode);
+    }
+
+    if let Some(problem) = problem {
+        println!("{}", problem);
+        state.message = Some(problem);
+    }
+
+    state
+}
+
+//Loads the game given with `--load PATH` or `--import PATH`, replays the one
+//given with `--replay PATH` or sets up the one given with `--position FEN`, if
+//there is one. Otherwise starts a new game. Also says whether the game came
+//from a save.
+fn load_or_make_state(seed: usize) -> (State, bool) {
+    let from_save = cli_value("--load").is_some();
+
+    let loaded = if from_save {
+        save::load(&save_path()).map(load_state).map_err(|e| e.to_string())
+    } else if let Some(path) = cli_value("--import") {
+        record::import(&PathBuf::from(path))
+            .map(import_state)
+            .map_err(|e| e.to_string())
+    } else if let Some(path) = cli_value("--replay") {
+        record::import(&PathBuf::from(path))
+            .map(|record| {
+                let mut state = make_state(seed, deck_setting());
+                state.replay = Some(Replay::new(record));
+                state
+            })
+            .map_err(|e| e.to_string())
+    } else if let Some(position) = cli_value("--position") {
+        position
+            .parse()
+            .map(|game| position_state(seed, game))
+            .map_err(|e: fen::FenError| e.to_string())
+    } else {
+        return (make_state(seed, deck_setting()), false);
+    };
+
+    match loaded {
+        Ok(state) => (state, from_save),
+        Err(e) => {
+            println!("{}", e);
+
+            let mut state = make_state(seed, deck_setting());
+            state.message = Some(format!("Could not load: {}", e));
+            (state, false)
+        }
+    }
+}
+
+fn import_state(record: GameRecord) -> State {
+    let mut state = make_state(record.seed.unwrap_or(0), deck_setting());
+
+    state.game = record.game;
+    state.history = record.history;
+    state.turn = turn_for(&state.game, state.mode);
+
+    if !state.history.is_empty() {
+        state.first_move_card = None;
+    }
+
+    state
+}
+
+fn position_state(seed: usize, game: GameState) -> State {
+    let mut state = make_state(seed, deck_setting());
+
+    state.game = game;
+    state.turn = turn_for(&state.game, state.mode);
+    state.first_move_card = None;
+
+    state
+}
+
+fn player_names(state: &State) -> (String, String) {
+    let cpu = format!("CPU ({})", state.cpu);
+
+    match state.mode {
+        Mode::VsCpu(Blue) => ("Player".to_string(), cpu),
+        Mode::VsCpu(Red) => (cpu, "Player".to_string()),
+        Mode::HotSeat => ("Blue player".to_string(), "Red player".to_string()),
+        Mode::Spectate => (format!("CPU ({})", state.blue_cpu), cpu),
+    }
+}
+
+fn game_record(state: &State) -> GameRecord {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|dur| dur.as_secs())
+        .unwrap_or(0);
+
+    let (blue, red) = player_names(state);
+
+    GameRecord::new(state, record::date_from_unix(seconds), blue, red)
+}
+
+fn export_record(state: &State) -> Result<PathBuf, String> {
+    let game_record = game_record(state);
+    let path = PathBuf::from(RECORD_PATH);
+
+    record::export(&game_record, &path)
+        .map(|_| path)
+        .map_err(|e| e.to_string())
+}
+
+fn load_state(saved: SavedGame) -> State {
+    let mut state = make_state(saved.seed, deck_setting());
+
+    state.game = saved.game;
+    state.history = saved.history;
+    state.cpu = saved.cpu;
+    state.mode = saved.mode;
+    state.side = saved.side;
+    state.turn = turn_for(&state.game, state.mode);
+
+    if !state.history.is_empty() {
+        state.first_move_card = None;
+    }
+
+    state
 }
 
 const CONTROL_COLOUR: Color = Color {
@@ -72,37 +277,32 @@ macro_rules! with_layer {
     }
 }
 
-fn make_state(mut rng: StdRng) -> State {
-    let mut board = [None; 25];
-    board[0] = Some(RedStudent);
-    board[1] = Some(RedStudent);
-    board[2] = Some(RedMaster);
-    board[3] = Some(RedStudent);
-    board[4] = Some(RedStudent);
-
-    board[20] = Some(BlueStudent);
-    board[21] = Some(BlueStudent);
-    board[22] = Some(BlueMaster);
-    board[23] = Some(BlueStudent);
-    board[24] = Some(BlueStudent);
-
-    let mut deck = Card::all_values();
-    rng.shuffle(&mut deck);
+fn make_state(seed: usize, deck: Deck) -> State {
+    let mut rng = seeded_rng(seed);
 
-    debug_assert!(deck.len() >= 5);
-
-    let player_cards = (deck.pop().unwrap(), deck.pop().unwrap());
-    let cpu_cards = (deck.pop().unwrap(), deck.pop().unwrap());
-
-    let center_card = deck.pop().unwrap();
+    let game = GameState::deal_from(&mut rng, deck);
 
     State {
         rng,
-        board,
-        player_cards,
-        center_card,
-        cpu_cards,
-        turn: Waiting,
+        seed,
+        game,
+        turn: turn_for(&game, Mode::default()),
+        history: History::new(),
+        preview: None,
+        history_scroll: 0,
+        replay: None,
+        editor: None,
+        cpu: CpuPlayer::default(),
+        blue_cpu: CpuPlayer::default(),
+        cpu_agents: [None, None],
+        thinking: None,
+        mode: Mode::default(),
+        side: Side::default(),
+        flip_board: false,
+        spectator: Spectator::default(),
+        deck,
+        first_move_card: Some(game.center_card),
+        message: None,
         show_credits: false,
         rotate_opponet_cards: true,
         ui_context: UIContext::new(),
@@ -112,12 +312,20 @@ fn make_state(mut rng: StdRng) -> State {
 #[no_mangle]
 //returns true if quit requested
 pub fn update_and_render(platform: &Platform, state: &mut State, events: &mut Vec<Event>) -> bool {
+    if let Some(problem) = install_custom_cards() {
+        state.message = Some(problem);
+    }
+
     let mut left_mouse_pressed = false;
     let mut left_mouse_released = false;
 
     for event in events {
         cross_mode_event_handling(platform, state, event);
 
+        if let Some(ref mut replay) = state.replay {
+            replay_event_handling(replay, event);
+        }
+
         match *event {
             Event::KeyPressed {
                 key: KeyCode::MouseLeft,
@@ -133,6 +341,28 @@ pub fn update_and_render(platform: &Platform, state: &mut State, events: &mut Ve
             } => {
                 left_mouse_released = true;
             }
+            Event::KeyPressed {
+                key: KeyCode::Z,
+                ctrl: true,
+                shift: _,
+            } => {
+                undo(state);
+            }
+            Event::KeyPressed {
+                key: KeyCode::Y,
+                ctrl: true,
+                shift: _,
+            } => {
+                redo(state);
+            }
+            Event::MouseScroll { delta } => {
+                //positive is down, towards the most recent plies
+                state.history_scroll = if delta > 0 {
+                    state.history_scroll.saturating_sub(delta as usize)
+                } else {
+                    state.history_scroll.saturating_add(-delta as usize)
+                };
+            }
             Event::Close |
             Event::KeyPressed {
                 key: KeyCode::Escape,
@@ -145,37 +375,80 @@ pub fn update_and_render(platform: &Platform, state: &mut State, events: &mut Ve
 
     state.ui_context.frame_init();
 
-    let first_clicked = !state.show_credits &&
-        do_card_button(
-            platform,
-            &mut state.ui_context,
-            6,
-            32,
-            &state.player_cards.0,
-            120,
-            left_mouse_pressed,
-            left_mouse_released,
-        );
+    if state.replay.is_some() {
+        do_replay(platform, state, left_mouse_pressed, left_mouse_released);
 
-    let second_clicked = !state.show_credits &&
-        do_card_button(
-            platform,
-            &mut state.ui_context,
-            42,
-            32,
-            &state.player_cards.1,
-            121,
-            left_mouse_pressed,
-            left_mouse_released,
-        );
+        return false;
+    }
+
+    if state.editor.is_some() {
+        do_editor(platform, state, left_mouse_pressed, left_mouse_released);
+
+        return false;
+    }
+
+    let shown = match state.preview {
+        Some(plies) => state.history.position_after(&state.game, plies),
+        None => state.game,
+    };
+
+    let viewer = viewer(state);
+    let flipped = viewer != Blue;
+
+    let mut clicked_card = None;
+
+    if !state.show_credits {
+        for &(colour, y, id) in [(viewer, 32, 120), (viewer.opponent(), 1, 122)].iter() {
+            let cards = shown.cards(colour);
+            let rotate = colour != viewer && state.rotate_opponet_cards;
+
+            if state.mode.is_cpu(colour) {
+                print_card(platform, 6, y, &cards.0, rotate);
+                print_card(platform, 42, y, &cards.1, rotate);
+                continue;
+            }
+
+            for &(x, card, pair_index, id) in
+                [(6, cards.0, First, id), (42, cards.1, Second, id + 1)].iter()
+            {
+                if do_card_button(
+                    platform,
+                    &mut state.ui_context,
+                    x,
+                    y,
+                    &card,
+                    rotate,
+                    id,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                ) && colour == state.game.to_move
+                {
+                    clicked_card = Some(pair_index);
+                }
+            }
+        }
+    }
+
+    let first_clicked = clicked_card == Some(First);
+    let second_clicked = clicked_card == Some(Second);
+
+    if first_clicked || second_clicked {
+        state.message = None;
+    }
 
     let possible_board_input = if state.show_credits {
         None
     } else {
         show_pieces(
             platform,
-            state,
-            400,
+            &mut state.ui_context,
+            &shown,
+            if state.preview.is_some() {
+                Waiting
+            } else {
+                state.turn
+            },
+            flipped,
             left_mouse_pressed,
             left_mouse_released,
         )
@@ -233,22 +506,9 @@ pub fn update_and_render(platform: &Platform, state: &mut State, events: &mut Ve
         Ryan Wiedemann (Ryan1729 on github)",
         );
     } else {
-        print_card(
-            platform,
-            6,
-            1,
-            &state.cpu_cards.0,
-            state.rotate_opponet_cards,
-        );
-        print_card(
-            platform,
-            42,
-            1,
-            &state.cpu_cards.1,
-            state.rotate_opponet_cards,
-        );
+        print_card(platform, 2, 16, &shown.center_card, false);
 
-        print_card(platform, 2, 16, &state.center_card, false);
+        do_history_panel(platform, state, left_mouse_pressed, left_mouse_released);
 
         with_foreground!(platform, CONTROL_COLOUR, {
             let new_game_spec = ButtonSpec {
@@ -270,14 +530,242 @@ pub fn update_and_render(platform: &Platform, state: &mut State, events: &mut Ve
                 left_mouse_released,
             )
             {
-                *state = make_state(state.rng);
+                let cpu = state.cpu;
+                let blue_cpu = state.blue_cpu;
+                let mode = state.mode;
+                let side = state.side;
+                let flip_board = state.flip_board;
+                let spectator = state.spectator.clone();
+                let seed = state.rng.gen();
+
+                *state = make_state(seed, state.deck);
+
+                state.cpu = cpu;
+                state.blue_cpu = blue_cpu;
+                state.spectator = spectator;
+                state.mode = new_game_mode(mode, side, &mut state.rng);
+                state.side = side;
+                state.flip_board = flip_board;
+                state.turn = turn_for(&state.game, state.mode);
+            }
+
+            let save_spec = ButtonSpec {
+                base: BlankButtonSpec {
+                    x: 19,
+                    y: 24,
+                    w: 8,
+                    h: 3,
+                    id: 13,
+                },
+                text: "Save".to_string(),
+            };
+
+            if do_button(
+                platform,
+                &mut state.ui_context,
+                &save_spec,
+                left_mouse_pressed,
+                left_mouse_released,
+            )
+            {
+                let path = save_path();
+
+                state.message = Some(match save::save(state, &path) {
+                    Ok(()) => format!("Saved to {}", path.display()),
+                    Err(e) => format!("Could not save: {}", e),
+                });
+            }
+
+            let load_spec = ButtonSpec {
+                base: BlankButtonSpec {
+                    x: 27,
+                    y: 24,
+                    w: 8,
+                    h: 3,
+                    id: 14,
+                },
+                text: "Load".to_string(),
+            };
+
+            if do_button(
+                platform,
+                &mut state.ui_context,
+                &load_spec,
+                left_mouse_pressed,
+                left_mouse_released,
+            )
+            {
+                let path = save_path();
+
+                match save::load(&path) {
+                    Ok(saved) => {
+                        let deck = state.deck;
+                        let mode = state.mode;
+                        let side = state.side;
+                        let flip_board = state.flip_board;
+
+                        *state = load_state(saved);
+
+                        state.deck = deck;
+                        state.mode = mode;
+                        state.side = side;
+                        state.flip_board = flip_board;
+                        state.turn = turn_for(&state.game, mode);
+                        state.message = Some(format!("Loaded {}", path.display()));
+                    }
+                    Err(e) => {
+                        state.message = Some(format!("Could not load: {}", e));
+                    }
+                }
+            }
+
+            if state.mode == Mode::Spectate {
+                let pause_spec = ButtonSpec {
+                    base: BlankButtonSpec {
+                        x: 2,
+                        y: 13,
+                        w: 7,
+                        h: 3,
+                        id: 33,
+                    },
+                    text: (if state.spectator.paused { "Play" } else { "Pause" }).to_string(),
+                };
+
+                if do_button(
+                    platform,
+                    &mut state.ui_context,
+                    &pause_spec,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    state.spectator.toggle_pause();
+                }
+
+                let step_spec = ButtonSpec {
+                    base: BlankButtonSpec {
+                        x: 9,
+                        y: 13,
+                        w: 6,
+                        h: 3,
+                        id: 34,
+                    },
+                    text: "Step".to_string(),
+                };
+
+                if do_button(
+                    platform,
+                    &mut state.ui_context,
+                    &step_spec,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    state.spectator.step();
+                }
+            } else {
+                let undo_spec = ButtonSpec {
+                    base: BlankButtonSpec {
+                        x: 2,
+                        y: 13,
+                        w: 6,
+                        h: 3,
+                        id: 10,
+                    },
+                    text: "Undo".to_string(),
+                };
+
+                if do_button(
+                    platform,
+                    &mut state.ui_context,
+                    &undo_spec,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    undo(state);
+                }
+
+                let redo_spec = ButtonSpec {
+                    base: BlankButtonSpec {
+                        x: 8,
+                        y: 13,
+                        w: 6,
+                        h: 3,
+                        id: 11,
+                    },
+                    text: "Redo".to_string(),
+                };
+
+                if do_button(
+                    platform,
+                    &mut state.ui_context,
+                    &redo_spec,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    redo(state);
+                }
+            }
+
+            //when two CPUs play, these are for Red's and Blue's are with the
+            //other settings
+            let cpu_label = if state.mode == Mode::Spectate { "Red" } else { "CPU" };
+
+            let difficulty_spec = ButtonSpec {
+                base: BlankButtonSpec {
+                    x: 15,
+                    y: 10,
+                    w: 20,
+                    h: 3,
+                    id: 8,
+                },
+                text: format!("{}: {}", cpu_label, state.cpu.difficulty),
+            };
+
+            if do_button(
+                platform,
+                &mut state.ui_context,
+                &difficulty_spec,
+                left_mouse_pressed,
+                left_mouse_released,
+            )
+            {
+                state.cpu.difficulty = state.cpu.difficulty.next();
+            }
+
+            let engine_spec = ButtonSpec {
+                base: BlankButtonSpec {
+                    x: 15,
+                    y: 13,
+                    w: 20,
+                    h: 3,
+                    id: 9,
+                },
+                text: if state.mode == Mode::Spectate {
+                    format!("Red: {}", state.cpu.engine)
+                } else {
+                    format!("Engine: {}", state.cpu.engine)
+                },
+            };
+
+            if do_button(
+                platform,
+                &mut state.ui_context,
+                &engine_spec,
+                left_mouse_pressed,
+                left_mouse_released,
+            )
+            {
+                state.cpu.engine = state.cpu.engine.next();
             }
 
             let rotate_spec = ButtonSpec {
                 base: BlankButtonSpec {
                     x: 2,
                     y: 27,
-                    w: 30,
+                    w: 26,
                     h: 3,
                     id: 7,
                 },
@@ -295,437 +783,1090 @@ pub fn update_and_render(platform: &Platform, state: &mut State, events: &mut Ve
                 state.rotate_opponet_cards = !state.rotate_opponet_cards;
             }
 
+            let position_spec = ButtonSpec {
+                base: BlankButtonSpec {
+                    x: 28,
+                    y: 27,
+                    w: 7,
+                    h: 3,
+                    id: 17,
+                },
+                text: "FEN".to_string(),
+            };
+
+            if do_button(
+                platform,
+                &mut state.ui_context,
+                &position_spec,
+                left_mouse_pressed,
+                left_mouse_released,
+            )
+            {
+                //there's no clipboard access from here, so the position goes
+                //to stdout and a file as well as the screen.
+                let position = state.game.to_string();
+
+                println!("{}", position);
+
+                state.message = Some(match std::fs::write(POSITION_PATH, &position) {
+                    Ok(()) => position,
+                    Err(e) => format!("Could not write {}: {}", POSITION_PATH, e),
+                });
+            }
+
+            let deck_spec = ButtonSpec {
+                base: BlankButtonSpec {
+                    x: 2,
+                    y: SETTINGS_Y,
+                    w: 24,
+                    h: 3,
+                    id: 19,
+                },
+                text: format!("Deck: {}", state.deck),
+            };
+
+            if do_button(
+                platform,
+                &mut state.ui_context,
+                &deck_spec,
+                left_mouse_pressed,
+                left_mouse_released,
+            )
+            {
+                state.deck = state.deck.next();
+                state.message = Some(format!("New games will use the {} deck", state.deck));
+            }
+
+            let mode_spec = ButtonSpec {
+                base: BlankButtonSpec {
+                    x: 27,
+                    y: SETTINGS_Y,
+                    w: 22,
+                    h: 3,
+                    id: 30,
+                },
+                text: format!("Mode: {}", state.mode),
+            };
+
+            if do_button(
+                platform,
+                &mut state.ui_context,
+                &mode_spec,
+                left_mouse_pressed,
+                left_mouse_released,
+            )
+            {
+                state.mode = match state.mode {
+                    Mode::VsCpu(_) => Mode::HotSeat,
+                    Mode::HotSeat => Mode::Spectate,
+                    Mode::Spectate => Mode::VsCpu(state.side.colour(&mut state.rng)),
+                };
+                state.turn = turn_for(&state.game, state.mode);
+            }
+
+            if state.mode == Mode::HotSeat {
+                let flip_spec = ButtonSpec {
+                    base: BlankButtonSpec {
+                        x: 50,
+                        y: SETTINGS_Y,
+                        w: 20,
+                        h: 3,
+                        id: 31,
+                    },
+                    text: format!("Flip board: {}", if state.flip_board { "on" } else { "off" }),
+                };
+
+                if do_button(
+                    platform,
+                    &mut state.ui_context,
+                    &flip_spec,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    state.flip_board = !state.flip_board;
+                }
+            } else if state.mode == Mode::Spectate {
+                let blue_difficulty_spec = ButtonSpec {
+                    base: BlankButtonSpec {
+                        x: 50,
+                        y: SETTINGS_Y,
+                        w: 18,
+                        h: 3,
+                        id: 35,
+                    },
+                    text: format!("Blue: {}", state.blue_cpu.difficulty),
+                };
+
+                if do_button(
+                    platform,
+                    &mut state.ui_context,
+                    &blue_difficulty_spec,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    state.blue_cpu.difficulty = state.blue_cpu.difficulty.next();
+                }
+
+                let blue_engine_spec = ButtonSpec {
+                    base: BlankButtonSpec {
+                        x: 68,
+                        y: SETTINGS_Y,
+                        w: 16,
+                        h: 3,
+                        id: 36,
+                    },
+                    text: format!("Blue: {}", state.blue_cpu.engine),
+                };
+
+                if do_button(
+                    platform,
+                    &mut state.ui_context,
+                    &blue_engine_spec,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    state.blue_cpu.engine = state.blue_cpu.engine.next();
+                }
+            } else {
+                let side_spec = ButtonSpec {
+                    base: BlankButtonSpec {
+                        x: 50,
+                        y: SETTINGS_Y,
+                        w: 20,
+                        h: 3,
+                        id: 32,
+                    },
+                    text: format!("Play as: {}", state.side),
+                };
+
+                if do_button(
+                    platform,
+                    &mut state.ui_context,
+                    &side_spec,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    state.side = state.side.next();
+                    state.message = Some(match state.side {
+                        Side::Random => "New games will give you a random side".to_string(),
+                        side => format!("New games will have you play {}", side),
+                    });
+                }
+            }
+
+            let edit_spec = ButtonSpec {
+                base: BlankButtonSpec {
+                    x: HISTORY_X + 10,
+                    y: 0,
+                    w: 8,
+                    h: 3,
+                    id: 18,
+                },
+                text: "Edit".to_string(),
+            };
+
+            if do_button(
+                platform,
+                &mut state.ui_context,
+                &edit_spec,
+                left_mouse_pressed,
+                left_mouse_released,
+            )
+            {
+                state.preview = None;
+                state.message = None;
+                state.editor = Some(Editor::new(state.game));
+            }
+
         });
     }
+    if let Some(ref message) = state.message {
+        if !state.show_credits {
+            (platform.print_xy)(2, 9, message);
+        }
+    }
+
+    if !state.show_credits {
+        if state.mode == Mode::Spectate {
+            do_spectator_panel(platform, state, left_mouse_pressed, left_mouse_released);
+        } else if !matches!(state.turn, MustPass | MovingSpirit(_, _)) {
+            if let Some(card) = state.first_move_card {
+                (platform.print_xy)(2, 30, &format!("The {}'s stamp is {},", card, card.stamp()));
+                (platform.print_xy)(
+                    2,
+                    31,
+                    match (state.mode, card.stamp()) {
+                        (Mode::VsCpu(player), stamp) if player == stamp => "so you move first.",
+                        (Mode::VsCpu(_), _) => "so the CPU moves first.",
+                        (_, Blue) => "so Blue moves first.",
+                        (_, Red) => "so Red moves first.",
+                    },
+                );
+            } else if state.mode == Mode::HotSeat && state.game.outcome().is_none() {
+                (platform.print_xy)(2, 30, &format!("{} to move.", state.game.to_move));
+            }
+        }
+    }
+
     let t = state.turn;
 
     if let Some(board_input) = possible_board_input {
         state.turn = board_input;
-    } else {
+    } else if state.preview.is_none() {
         match state.turn {
             Waiting => {
+                if state.game.must_pass() {
+                    state.turn = MustPass;
+                } else if first_clicked {
+                    state.turn = SelectedCard(First);
+                } else if second_clicked {
+                    state.turn = SelectedCard(Second);
+                }
+            }
+            SelectedCard(_) => {
                 if first_clicked {
                     state.turn = SelectedCard(First);
                 } else if second_clicked {
                     state.turn = SelectedCard(Second);
                 }
             }
-            SelectedCard(pair_index) => {
+            SelectedPiece(pair_index, source_index) => {
                 if first_clicked {
                     state.turn = SelectedCard(First);
                 } else if second_clicked {
                     state.turn = SelectedCard(Second);
                 }
 
-                if get_moves(&state.board, &state.player_cards, Blue).len() == 0 {
-                    if cfg!(debug_assertions) {
-                        println!(
-                            "player swapping {:?} for {:?}",
-                            state.center_card,
-                            match pair_index {
-                                First => state.player_cards.0,
-                                Second => state.player_cards.1,
-                            }
-                        );
-                    }
+                let targets = piece_targets(&state.game, pair_index, source_index);
+                let colour = state.game.to_move;
+
+                if let Some(target_index) = do_target_buttons(
+                    platform,
+                    &mut state.ui_context,
+                    &targets,
+                    colour,
+                    flipped,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    let m = Move {
+                        source_index,
+                        target_index,
+                    };
 
-                    swap_cards(&mut state.center_card, &mut state.player_cards, pair_index);
+                    if state.game.card(colour, pair_index).is_wind() {
+                        state.turn = MovingSpirit(pair_index, m);
+                    } else {
+                        state.history.play(&mut state.game, Ply::Move(m, pair_index));
+                        state.first_move_card = None;
+
+                        state.turn = turn_for(&state.game, state.mode);
+                    }
                 }
             }
-            SelectedPiece(pair_index, source_index) => {
+            MovingSpirit(pair_index, piece) => {
+                (platform.print_xy)(2, 30, "Now choose where the");
+                (platform.print_xy)(2, 31, "Wind Spirit goes.");
+
                 if first_clicked {
                     state.turn = SelectedCard(First);
                 } else if second_clicked {
                     state.turn = SelectedCard(Second);
                 }
 
-                let mut have_not_moved = true;
-                let mut counter = 0;
-                for &(x_usize, y_usize) in
-                    valid_move_locations(
-                        &state.board,
-                        match pair_index {
-                            First => &state.player_cards.0,
-                            Second => &state.player_cards.1,
-                        },
-                        source_index,
-                        Blue,
-                    ).iter()
+                let colour = state.game.to_move;
+
+                if let Some((x, y)) = screen_xy(piece.target_index, flipped) {
+                    with_layer!(platform, 3, {
+                        (platform.print_xy)(
+                            piece_x(x),
+                            piece_y(y),
+                            &highlight_char(colour).to_string(),
+                        );
+                    })
+                }
+
+                let targets = spirit_targets(&state.game, pair_index, piece);
+
+                if let Some(target_index) = do_target_buttons(
+                    platform,
+                    &mut state.ui_context,
+                    &targets,
+                    colour,
+                    flipped,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
                 {
-                    let x = x_usize as i32;
-                    let y = y_usize as i32;
-                    if do_blank_button(
+                    if let Some(spirit_index) = get_spirit_index(&state.game.board) {
+                        let spirit = Move {
+                            source_index: spirit_index,
+                            target_index,
+                        };
+
+                        state.history.play(
+                            &mut state.game,
+                            Ply::Wind(WindMove { piece, spirit }, pair_index),
+                        );
+                        state.first_move_card = None;
+
+                        state.turn = turn_for(&state.game, state.mode);
+                    }
+                }
+            }
+            MustPass => {
+                (platform.print_xy)(2, 30, "You have no legal moves.");
+                (platform.print_xy)(2, 31, "Choose a card to exchange.");
+
+                let pair_index = if first_clicked {
+                    Some(First)
+                } else if second_clicked {
+                    Some(Second)
+                } else {
+                    None
+                };
+
+                if let Some(pair_index) = pair_index {
+                    state.history.play(&mut state.game, Ply::Pass(pair_index));
+                    state.first_move_card = None;
+
+                    state.turn = turn_for(&state.game, state.mode);
+                }
+            }
+            CpuTurn => {
+                let can_start = state.mode != Mode::Spectate ||
+                    state.spectator.ready(Instant::now());
+
+                if state.thinking.is_none() && can_start {
+                    let colour = state.game.to_move;
+                    let player = cpu_for(state, colour);
+                    let mut cpu = state.cpu_agents[colour as usize]
+                        .take()
+                        .unwrap_or_else(|| Cpu::new(player));
+                    cpu.player = player;
+
+                    let rng = seeded_rng(state.rng.gen());
+                    state.thinking = Some(Thinking::start(cpu, state.game, rng));
+                }
+
+                finish_thinking(state);
+            }
+            Over(winner_colour) => {
+                (platform.print_xy)(10, 14, &format!("{} team wins", winner_colour));
+
+                with_foreground!(platform, CONTROL_COLOUR, {
+                    let export_spec = ButtonSpec {
+                        base: BlankButtonSpec {
+                            x: HISTORY_X,
+                            y: HISTORY_TOP + HISTORY_ROWS as i32 + 1,
+                            w: HISTORY_W / 2,
+                            h: 3,
+                            id: 15,
+                        },
+                        text: "Export".to_string(),
+                    };
+
+                    if do_button(
                         platform,
                         &mut state.ui_context,
-                        &BlankButtonSpec {
-                            x: piece_x(x) - 4,
-                            y: piece_y(y) - 2,
-                            w: PIECE_BUTTON_WIDTH,
-                            h: PIECE_BUTTON_HEIGHT,
-                            id: 450 + counter,
-                        },
+                        &export_spec,
                         left_mouse_pressed,
                         left_mouse_released,
-                    ) && have_not_moved
+                    )
                     {
-                        if let Some(target_index) = get_board_index(x_usize, y_usize) {
-                            state.board = apply_move(
-                                &state.board,
-                                Move {
-                                    source_index,
-                                    target_index,
-                                },
-                            );
-
-                            if cfg!(debug_assertions) {
-                                println!(
-                                    "player moving from {} to {} with {:?}",
-                                    source_index,
-                                    target_index,
-                                    match pair_index {
-                                        First => state.player_cards.0,
-                                        Second => state.player_cards.1,
-                                    }
-                                );
-                            }
-
-                            swap_cards(&mut state.center_card, &mut state.player_cards, pair_index);
-
-                            state.turn = winner(&state.board).unwrap_or(CpuTurn);
+                        state.message = Some(match export_record(state) {
+                            Ok(path) => format!("Exported to {}", path.display()),
+                            Err(e) => format!("Could not export: {}", e),
+                        });
+                    }
 
-                            have_not_moved = false;
-                        }
+                    let replay_spec = ButtonSpec {
+                        base: BlankButtonSpec {
+                            x: HISTORY_X + HISTORY_W / 2,
+                            y: HISTORY_TOP + HISTORY_ROWS as i32 + 1,
+                            w: HISTORY_W / 2,
+                            h: 3,
+                            id: 16,
+                        },
+                        text: "Replay".to_string(),
+                    };
 
+                    if do_button(
+                        platform,
+                        &mut state.ui_context,
+                        &replay_spec,
+                        left_mouse_pressed,
+                        left_mouse_released,
+                    )
+                    {
+                        state.replay = Some(Replay::new(game_record(state)));
                     }
-                    counter += 1;
-
-                    with_layer!(platform, 3, {
-                        (platform.print_xy)(piece_x(x), piece_y(y), &BLUE_HIGHLIGHT.to_string());
-                    })
-                }
+                });
             }
-            CpuTurn => {
-                let moves = get_moves(&state.board, &state.cpu_cards, Red);
+        }
+    }
 
-                let mut not_moved = true;
+    if cfg!(debug_assertions) {
+        if t != state.turn {
+            println!("{:?}", state.turn);
+        }
+    }
 
-                for &(current_move, pair_index) in moves.iter() {
-                    let new_board = apply_move(&state.board, current_move);
+    false
+}
 
-                    if red_wins(&new_board) {
-                        state.board = new_board;
+//Against the CPU, undoing and redoing go a whole turn at a time, so the CPU's
+//reply comes and goes along with the player's ply. In hot-seat games they go
+//one ply at a time.
+fn undo(state: &mut State) {
+    if state.turn == CpuTurn || state.replay.is_some() || state.editor.is_some() {
+        return;
+    }
 
-                        swap_cards(&mut state.center_card, &mut state.cpu_cards, pair_index);
+    state.preview = None;
 
-                        not_moved = false;
-                        break;
-                    }
-                }
+    let undone = match state.mode {
+        Mode::VsCpu(player) => state.history.undo_turn(&mut state.game, player),
+        Mode::HotSeat | Mode::Spectate => state.history.undo(&mut state.game).is_some(),
+    };
 
-                if not_moved {
-                    let mut viable_moves = Vec::new();
-                    //these shouldn't contain inviable moves either
-                    let mut no_player_capture_moves = Vec::new();
-                    let mut capturing_moves = Vec::new();
-
-                    let blue_before = piece_count_of_colour(&state.board, Blue);
-                    for &(current_move, pair_index) in moves.iter() {
-                        let one_move_board = apply_move(&state.board, current_move);
-
-                        let player_moves = get_moves(&one_move_board, &state.player_cards, Blue);
-
-                        let player_has_no_winning_move =
-                            !player_moves.iter().any(|&(player_move, _)| {
-                                blue_wins(&apply_move(&one_move_board, player_move))
-                            });
-                        if player_has_no_winning_move {
-                            viable_moves.push((current_move, pair_index));
-
-                            let red_before = piece_count_of_colour(&one_move_board, Red);
-                            let player_cannot_capture =
-                                !player_moves.iter().any(|&(player_move, _)| {
-                                    let after = piece_count_of_colour(
-                                        &apply_move(&one_move_board, player_move),
-                                        Red,
-                                    );
-
-                                    red_before > after
-                                });
-
-                            if player_cannot_capture {
-                                no_player_capture_moves.push((current_move, pair_index));
-                            }
+    if undone {
+        state.turn = turn_for(&state.game, state.mode);
+    }
+}
 
-                            let captured = blue_before >
-                                piece_count_of_colour(&one_move_board, Blue);
+fn redo(state: &mut State) {
+    if state.turn == CpuTurn || state.replay.is_some() || state.editor.is_some() {
+        return;
+    }
 
-                            if captured {
-                                capturing_moves.push((current_move, pair_index));
-                            }
-                        }
-                    }
+    state.preview = None;
 
-                    let undefended_captures: Vec<_> = capturing_moves
-                        .iter()
-                        .filter(|x| no_player_capture_moves.contains(x))
-                        .map(|x| *x)
-                        .collect();
+    let redone = match state.mode {
+        Mode::VsCpu(player) => state.history.redo_turn(&mut state.game, player),
+        Mode::HotSeat | Mode::Spectate => state.history.redo(&mut state.game).is_some(),
+    };
 
-                    if not_moved && undefended_captures.len() > 0 {
-                        make_random_cpu_move_from_vec(state, undefended_captures);
-                        not_moved = false;
-                    }
+    if redone {
+        state.turn = turn_for(&state.game, state.mode);
+    }
+}
 
-                    let agressive = state.rng.gen::<bool>();
+enum ReplayControl {
+    Start,
+    Back,
+    Forward,
+    End,
+    Autoplay,
+    Speed,
+    Exit,
+}
 
-                    let (first_priority, second_priority) = if agressive {
-                        (capturing_moves, no_player_capture_moves)
-                    } else {
-                        (no_player_capture_moves, capturing_moves)
-                    };
+//Shows one position of a recorded game at a time, with the last ply
+//highlighted. Leaving the replay goes back to whatever was there before.
+fn do_replay(
+    platform: &Platform,
+    state: &mut State,
+    left_mouse_pressed: bool,
+    left_mouse_released: bool,
+) {
+    let mut exit = false;
 
-                    if not_moved && first_priority.len() > 0 {
-                        make_random_cpu_move_from_vec(state, first_priority);
-                        not_moved = false;
-                    }
+    if let Some(ref mut replay) = state.replay {
+        replay.update(Instant::now());
 
-                    if not_moved && second_priority.len() > 0 {
-                        make_random_cpu_move_from_vec(state, second_priority);
-                        not_moved = false;
-                    }
+        let game = replay.position();
 
-                    if not_moved && viable_moves.len() > 0 {
-                        make_random_cpu_move_from_vec(state, viable_moves);
-                        not_moved = false;
-                    } else if cfg!(debug_assertions) && not_moved {
-                        println!("player can win");
-                    }
-                }
+        show_pieces(
+            platform,
+            &mut state.ui_context,
+            &game,
+            Waiting,
+            false,
+            left_mouse_pressed,
+            left_mouse_released,
+        );
+
+        if let Some(record) = replay.last_ply() {
+            let highlight = highlight_char(record.colour);
 
-                if not_moved {
-                    make_random_cpu_move_from_vec(state, moves);
+            let indices = match record.ply {
+                Ply::Move(m, _) => vec![m.source_index, m.target_index],
+                Ply::Wind(w, _) => {
+                    vec![w.piece.source_index, w.piece.target_index, w.spirit.target_index]
                 }
+                Ply::Pass(_) => Vec::new(),
+            };
 
-                state.turn = winner(&state.board).unwrap_or(Waiting);
+            for index in indices {
+                if let Some((x, y)) = get_board_xy(index) {
+                    with_layer!(platform, 3, {
+                        (platform.print_xy)(
+                            piece_x(x as i32),
+                            piece_y(y as i32),
+                            &highlight.to_string(),
+                        );
+                    })
+                }
             }
-            Over(winner_colour) => {
-                (platform.print_xy)(10, 14, &format!("{} team wins", winner_colour));
+        }
+
+        print_card(platform, 6, 1, &game.red_cards.0, state.rotate_opponet_cards);
+        print_card(platform, 42, 1, &game.red_cards.1, state.rotate_opponet_cards);
+        print_card(platform, 2, 16, &game.center_card, false);
+        print_card(platform, 6, 32, &game.blue_cards.0, false);
+        print_card(platform, 42, 32, &game.blue_cards.1, false);
+
+        let record = &replay.record;
+
+        (platform.print_xy)(2, 24, &format!("Blue: {}", record.blue));
+        (platform.print_xy)(2, 25, &format!("Red: {}", record.red));
+
+        (platform.print_xy)(
+            2,
+            30,
+            &match replay.last_ply() {
+                Some(last) => ply_text(replay.shown, last),
+                None => "Start of the game".to_string(),
+            },
+        );
+        (platform.print_xy)(
+            2,
+            31,
+            &match record.result() {
+                Some((winner, reason)) => format!("{} won by the {}", winner, reason),
+                None => "Unfinished".to_string(),
+            },
+        );
+
+        let delay = format!("Every {}s", replay.delay().as_millis() as f32 / 1000.0);
+
+        let controls = [
+            (2, 10, 5, "|<", 20, ReplayControl::Start),
+            (7, 10, 5, "<", 21, ReplayControl::Back),
+            (12, 10, 5, ">", 22, ReplayControl::Forward),
+            (17, 10, 5, ">|", 23, ReplayControl::End),
+            (
+                2,
+                13,
+                10,
+                if replay.autoplay { "Pause" } else { "Play" },
+                24,
+                ReplayControl::Autoplay,
+            ),
+            (12, 13, 16, &delay, 25, ReplayControl::Speed),
+            (2, 27, 16, "Exit replay", 26, ReplayControl::Exit),
+        ];
+
+        let mut clicked = None;
+
+        with_foreground!(platform, CONTROL_COLOUR, {
+            for (x, y, w, text, id, control) in controls {
+                let spec = ButtonSpec {
+                    base: BlankButtonSpec { x, y, w, h: 3, id },
+                    text: text.to_string(),
+                };
+
+                if do_button(
+                    platform,
+                    &mut state.ui_context,
+                    &spec,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    clicked = Some(control);
+                }
             }
+        });
+
+        match clicked {
+            Some(ReplayControl::Start) => replay.to_start(),
+            Some(ReplayControl::Back) => replay.back(),
+            Some(ReplayControl::Forward) => replay.forward(),
+            Some(ReplayControl::End) => replay.to_end(),
+            Some(ReplayControl::Autoplay) => replay.toggle_autoplay(Instant::now()),
+            Some(ReplayControl::Speed) => replay.next_speed(),
+            Some(ReplayControl::Exit) => exit = true,
+            None => {}
         }
     }
 
-    if cfg!(debug_assertions) {
-        if t != state.turn {
-            println!("{:?}", state.turn);
-        }
+    if exit {
+        state.replay = None;
     }
+}
 
-    false
+enum EditorControl {
+    ToMove,
+    Play(Mode),
+    Cancel,
 }
 
-fn piece_count_of_colour(board: &Board, piece_colour: PieceColour) -> usize {
-    board
-        .iter()
-        .filter(|p| {
-            p.map(|piece| piece.colour() == piece_colour).unwrap_or(
+//Clicking a square cycles through the pieces that can go on it and clicking a
+//card cycles through the cards. Playing from the edited position starts a new
+//game against the CPU, as long as the position is one a game could have.
+fn do_editor(
+    platform: &Platform,
+    state: &mut State,
+    left_mouse_pressed: bool,
+    left_mouse_released: bool,
+) {
+    let mut finished = None;
+    let mut cancel = false;
+
+    if let Some(ref mut editor) = state.editor {
+        show_pieces(
+            platform,
+            &mut state.ui_context,
+            &editor.game,
+            Waiting,
+            false,
+            left_mouse_pressed,
+            left_mouse_released,
+        );
+
+        let mut clicked_square = None;
+
+        for index in 0..editor.game.board.len() {
+            if let Some((x, y)) = get_board_xy(index) {
+                if do_blank_button(
+                    platform,
+                    &mut state.ui_context,
+                    &BlankButtonSpec {
+                        x: piece_x(x as i32) - 4,
+                        y: piece_y(y as i32) - 2,
+                        w: PIECE_BUTTON_WIDTH,
+                        h: PIECE_BUTTON_HEIGHT,
+                        id: 450 + index as UiId,
+                    },
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    clicked_square = Some(index);
+                }
+            }
+        }
+
+        if let Some(index) = clicked_square {
+            editor.cycle_square(index);
+        }
+
+        let slots = [
+            (6, 1, CardSlot::Red(First), 122),
+            (42, 1, CardSlot::Red(Second), 123),
+            (2, 16, CardSlot::Center, 124),
+            (6, 32, CardSlot::Blue(First), 120),
+            (42, 32, CardSlot::Blue(Second), 121),
+        ];
+
+        let mut clicked_slot = None;
+
+        for &(x, y, slot, id) in slots.iter() {
+            if do_card_button(
+                platform,
+                &mut state.ui_context,
+                x,
+                y,
+                &editor.card(slot),
                 false,
+                id,
+                left_mouse_pressed,
+                left_mouse_released,
             )
-        })
-        .count()
-}
+            {
+                clicked_slot = Some(slot);
+            }
+        }
 
-fn make_random_cpu_move_from_vec(state: &mut State, moves: Vec<(Move, PairIndex)>) {
-    let len = moves.len();
-    if len == 0 {
-        let chosen_pair_index = state.rng.gen::<PairIndex>();
-        if cfg!(debug_assertions) {
-            println!(
-                "cpu swapping {:?} for {:?}",
-                state.center_card,
-                match chosen_pair_index {
-                    First => state.cpu_cards.0,
-                    Second => state.cpu_cards.1,
+        if let Some(slot) = clicked_slot {
+            editor.cycle_card(slot);
+        }
+
+        let to_move = format!("To move: {}", editor.game.to_move);
+
+        let controls = [
+            (2, 10, 20, to_move.as_str(), 27, EditorControl::ToMove),
+            (2, 13, 20, "Play vs CPU", 28, EditorControl::Play(Mode::default())),
+            (2, 24, 20, "Play vs friend", 30, EditorControl::Play(Mode::HotSeat)),
+            (2, 27, 16, "Cancel", 29, EditorControl::Cancel),
+        ];
+
+        let mut clicked = None;
+
+        with_foreground!(platform, CONTROL_COLOUR, {
+            for (x, y, w, text, id, control) in controls {
+                let spec = ButtonSpec {
+                    base: BlankButtonSpec { x, y, w, h: 3, id },
+                    text: text.to_string(),
+                };
+
+                if do_button(
+                    platform,
+                    &mut state.ui_context,
+                    &spec,
+                    left_mouse_pressed,
+                    left_mouse_released,
+                )
+                {
+                    clicked = Some(control);
                 }
-            );
+            }
+        });
+
+        match clicked {
+            Some(EditorControl::ToMove) => editor.toggle_to_move(),
+            Some(EditorControl::Play(mode)) => {
+                finished = editor.finish().map(|game| (game, mode));
+            }
+            Some(EditorControl::Cancel) => cancel = true,
+            None => {}
         }
 
-        //can't move so just pick a card to switch
-        swap_cards(
-            &mut state.center_card,
-            &mut state.cpu_cards,
-            chosen_pair_index,
-        );
-    } else {
-        let (random_move, pair_index) = moves[state.rng.gen_range(0, len)];
-
-        if cfg!(debug_assertions) {
-            println!(
-                "cpu moving from {} to {} with {:?}",
-                random_move.source_index,
-                random_move.target_index,
-                match pair_index {
-                    First => state.cpu_cards.0,
-                    Second => state.cpu_cards.1,
-                }
-            );
+        if let Some(ref problem) = editor.problem {
+            (platform.print_xy)(2, 9, &format!("Can't play from here: {}", problem));
         }
 
-        state.board = apply_move(&state.board, random_move);
+        (platform.print_xy)(2, 30, "Click a square to change its piece");
+        (platform.print_xy)(2, 31, "and a card to change the card.");
+    }
 
-        swap_cards(&mut state.center_card, &mut state.cpu_cards, pair_index);
+    if let Some((game, mode)) = finished {
+        let seed = state.rng.gen();
+        let cpu = state.cpu;
+        let blue_cpu = state.blue_cpu;
+        let deck = state.deck;
+        let side = state.side;
+        let flip_board = state.flip_board;
+        let rotate_opponet_cards = state.rotate_opponet_cards;
+
+        *state = position_state(seed, game);
+
+        state.cpu = cpu;
+        state.blue_cpu = blue_cpu;
+        state.deck = deck;
+        state.mode = new_game_mode(mode, side, &mut state.rng);
+        state.side = side;
+        state.flip_board = flip_board;
+        state.rotate_opponet_cards = rotate_opponet_cards;
+        state.turn = turn_for(&state.game, state.mode);
+    } else if cancel {
+        state.editor = None;
     }
 }
 
-fn get_moves(board: &Board, cards: &(Card, Card), colour: PieceColour) -> Vec<(Move, PairIndex)> {
-    let pieces = get_piece_indices(board, colour);
+fn replay_event_handling(replay: &mut Replay, event: &Event) {
+    if let Event::KeyPressed {
+        key,
+        ctrl: _,
+        shift: _,
+    } = *event
+    {
+        match key {
+            KeyCode::Left => replay.back(),
+            KeyCode::Right => replay.forward(),
+            KeyCode::Home => replay.to_start(),
+            KeyCode::End => replay.to_end(),
+            KeyCode::Space => replay.toggle_autoplay(Instant::now()),
+            _ => {}
+        }
+    }
+}
 
-    let mut result = Vec::new();
+fn turn_for(game: &GameState, mode: Mode) -> Turn {
+    match game.outcome() {
+        Some(winner) => Over(winner),
+        None if mode.is_cpu(game.to_move) => CpuTurn,
+        None => Waiting,
+    }
+}
 
-    for piece in pieces.iter() {
-        result.extend(valid_moves(board, &cards.0, *piece, colour).iter().map(
-            |m| {
-                (*m, First)
-            },
-        ));
-        result.extend(valid_moves(board, &cards.1, *piece, colour).iter().map(
-            |m| {
-                (*m, Second)
-            },
-        ));
+//Against the CPU, each new game picks the player's colour again from `side`.
+fn new_game_mode(mode: Mode, side: Side, rng: &mut StdRng) -> Mode {
+    match mode {
+        Mode::VsCpu(_) => Mode::VsCpu(side.colour(rng)),
+        Mode::HotSeat | Mode::Spectate => mode,
     }
+}
 
-    result
+//Whose side of the board is drawn at the bottom.
+fn viewer(state: &State) -> PieceColour {
+    match state.mode {
+        Mode::VsCpu(player) => player,
+        Mode::HotSeat if state.flip_board => state.game.to_move,
+        Mode::HotSeat | Mode::Spectate => Blue,
+    }
 }
 
-fn get_piece_indices(board: &Board, colour: PieceColour) -> Vec<usize> {
-    board
-        .iter()
-        .enumerate()
-        .filter_map(|(index, piece)| {
-            piece.and_then(|p| if p.colour() == colour {
-                Some(index)
-            } else {
-                None
-            })
+//Plays the ply the CPU chose once it has finished thinking. If the game has
+//changed since it started, say because of an undo, the ply is thrown away and
+//the CPU starts again the next frame if it is still its turn. A ply already
+//being thought about when spectating is paused still gets played.
+fn finish_thinking(state: &mut State) {
+    let finished = match state.thinking {
+        Some(ref thinking) => thinking.poll().map(|finished| (thinking.game, finished)),
+        None => None,
+    };
 
-        })
-        .collect()
-}
+    if let Some((game, (cpu, ply))) = finished {
+        state.thinking = None;
+        state.cpu_agents[game.to_move as usize] = Some(cpu);
 
-fn winner(board: &Board) -> Option<Turn> {
-    if blue_wins(board) {
-        Some(Over(Blue))
-    } else if red_wins(board) {
-        Some(Over(Red))
-    } else {
-        None
+        if game == state.game {
+            if let Some(ply) = ply {
+                state.history.play(&mut state.game, ply);
+            }
+
+            state.turn = turn_for(&state.game, state.mode);
+        }
     }
 }
 
-fn blue_wins(board: &Board) -> bool {
-    if let Some(master_index) = get_master_index(board, Blue) {
-        master_index == TOP_PAGODA_INDEX || get_master_index(board, Red).is_none()
-    } else {
-        false
+fn cpu_for(state: &State, colour: PieceColour) -> CpuPlayer {
+    match (state.mode, colour) {
+        (Mode::Spectate, Blue) => state.blue_cpu,
+        _ => state.cpu,
     }
 }
-fn red_wins(board: &Board) -> bool {
-    if let Some(master_index) = get_master_index(board, Red) {
-        master_index == BOTTOM_PAGODA_INDEX || get_master_index(board, Blue).is_none()
-    } else {
-        false
+
+const SPEED_X: i32 = 9;
+
+//The speed slider and who is thinking, in the space the player's notices use
+//in other modes.
+fn do_spectator_panel(
+    platform: &Platform,
+    state: &mut State,
+    left_mouse_pressed: bool,
+    left_mouse_released: bool,
+) {
+    (platform.print_xy)(2, 30, "Speed");
+
+    let slider_spec = BlankButtonSpec {
+        x: SPEED_X,
+        y: 30,
+        w: 2 * spectate::DELAYS.len() as i32,
+        h: 1,
+        id: 40,
+    };
+
+    if let Some(speed) = do_slider(
+        platform,
+        &mut state.ui_context,
+        &slider_spec,
+        state.spectator.speed,
+        spectate::DELAYS.len(),
+        left_mouse_pressed,
+        left_mouse_released,
+    )
+    {
+        state.spectator.set_speed(speed);
     }
+
+    let status = if let Over(_) = state.turn {
+        String::new()
+    } else if state.spectator.paused {
+        "Paused, Step plays one ply.".to_string()
+    } else {
+        let colour = state.game.to_move;
+
+        format!("{} ({}) to move.", colour, cpu_for(state, colour))
+    };
+
+    (platform.print_xy)(2, 31, &status);
 }
 
-fn get_master_index(board: &Board, colour: PieceColour) -> Option<usize> {
-    match colour {
-        Red => {
-            board.iter().position(|p| match *p {
-                Some(RedMaster) => true,
-                _ => false,
-            })
-        }
-        Blue => {
-            board.iter().position(|p| match *p {
-                Some(BlueMaster) => true,
-                _ => false,
-            })
+//A row of `count` notches, two columns each, filled in up to `value`. Returns
+//the notch that was clicked, if any.
+fn do_slider(
+    platform: &Platform,
+    context: &mut UIContext,
+    spec: &BlankButtonSpec,
+    value: usize,
+    count: usize,
+    left_mouse_pressed: bool,
+    left_mouse_released: bool,
+) -> Option<usize> {
+    let mut result = None;
+    let notch_w = spec.w / count as i32;
+
+    for i in 0..count {
+        let notch_spec = BlankButtonSpec {
+            x: spec.x + i as i32 * notch_w,
+            y: spec.y,
+            w: notch_w,
+            h: spec.h,
+            id: spec.id + i as UiId,
+        };
+
+        if button_interaction(
+            platform,
+            context,
+            &notch_spec,
+            left_mouse_pressed,
+            left_mouse_released,
+        )
+        {
+            result = Some(i);
         }
+
+        let notch = if context.hot == notch_spec.id {
+            "##"
+        } else if i <= value {
+            "=="
+        } else {
+            "--"
+        };
+
+        (platform.print_xy)(notch_spec.x, notch_spec.y, notch);
     }
+
+    result
 }
 
+//settings go along the bottom, under the cards
+const SETTINGS_Y: i32 = 40;
 
+const HISTORY_X: i32 = 78;
+const HISTORY_W: i32 = 26;
+const HISTORY_TOP: i32 = 3;
+const HISTORY_ROWS: usize = 33;
 
-//maybe this should also handle card switcing as well?
-fn apply_move(board: &Board, current_move: Move) -> Board {
-    let mut result = board.clone();
+//Lists every ply so far, most recent at the bottom. Clicking an entry shows
+//the position after that ply until it is clicked again.
+fn do_history_panel(
+    platform: &Platform,
+    state: &mut State,
+    left_mouse_pressed: bool,
+    left_mouse_released: bool,
+) {
+    (platform.print_xy)(HISTORY_X, 1, "History");
 
-    let piece = result[current_move.source_index];
-    result[current_move.target_index] = piece;
-    result[current_move.source_index] = None;
+    let records = state.history.records();
 
-    result
-}
+    state.history_scroll = std::cmp::min(
+        state.history_scroll,
+        records.len().saturating_sub(HISTORY_ROWS),
+    );
 
-fn swap_cards(center_card: &mut Card, cards: &mut (Card, Card), pair_index: PairIndex) {
-    let swapped = get_swap_cards_result(*center_card, *cards, pair_index);
+    let end = records.len() - state.history_scroll;
+    let start = end.saturating_sub(HISTORY_ROWS);
 
-    *center_card = swapped.center_card;
-    *cards = swapped.pair;
-}
-fn get_swap_cards_result(
-    center_card: Card,
-    cards: (Card, Card),
-    pair_index: PairIndex,
-) -> SwapCards {
-    match pair_index {
-        First => {
-            SwapCards {
-                center_card: cards.0,
-                pair: (center_card, cards.1),
-            }
+    with_foreground!(platform, CONTROL_COLOUR, {
+        if start > 0 {
+            (platform.print_xy)(HISTORY_X + HISTORY_W - 2, 1, "^");
         }
-        Second => {
-            SwapCards {
-                center_card: cards.1,
-                pair: (cards.0, center_card),
-            }
+        if end < records.len() {
+            (platform.print_xy)(HISTORY_X + HISTORY_W - 2, HISTORY_TOP + HISTORY_ROWS as i32, "v");
         }
+    });
+
+    let mut clicked = None;
+
+    for (i, record) in records.iter().enumerate().take(end).skip(start) {
+        let spec = ButtonSpec {
+            base: BlankButtonSpec {
+                x: HISTORY_X,
+                y: HISTORY_TOP + (i - start) as i32,
+                w: HISTORY_W,
+                h: 1,
+                id: 1000 + i as UiId,
+            },
+            text: ply_text(i + 1, record),
+        };
+
+        if do_text_button(
+            platform,
+            &mut state.ui_context,
+            &spec,
+            state.preview == Some(i + 1),
+            left_mouse_pressed,
+            left_mouse_released,
+        )
+        {
+            clicked = Some(i + 1);
+        }
+    }
+
+    if let Some(plies) = clicked {
+        state.preview = if state.preview == Some(plies) {
+            None
+        } else {
+            Some(plies)
+        };
+    }
+
+    if state.preview.is_some() {
+        with_foreground!(platform, CONTROL_COLOUR, {
+            let back_spec = ButtonSpec {
+                base: BlankButtonSpec {
+                    x: HISTORY_X,
+                    y: HISTORY_TOP + HISTORY_ROWS as i32 + 1,
+                    w: HISTORY_W,
+                    h: 3,
+                    id: 12,
+                },
+                text: "Back to game".to_string(),
+            };
+
+            if do_button(
+                platform,
+                &mut state.ui_context,
+                &back_spec,
+                left_mouse_pressed,
+                left_mouse_released,
+            )
+            {
+                state.preview = None;
+            }
+        });
     }
 }
 
-struct SwapCards {
-    center_card: Card,
-    pair: (Card, Card),
+//"3. Blue Tiger c5-c3x", numbered by ply.
+fn ply_text(number: usize, record: &Record) -> String {
+    format!("{}. {} {}", number, record.colour, Notation::from(record))
 }
 
+//The side to move can pick up their pieces, or the Wind Spirit, once they have
+//picked a card. When `flipped` the board is drawn from Red's side.
 fn show_pieces(
     platform: &Platform,
-    state: &mut State,
-    id_offset: UiId,
+    context: &mut UIContext,
+    game: &GameState,
+    turn: Turn,
+    flipped: bool,
     left_mouse_pressed: bool,
     left_mouse_released: bool,
 ) -> Option<Turn> {
+    let board = &game.board;
     let mut result = None;
 
     for y in 0..5 {
         for x in 0..5 {
             (platform.print_xy)(piece_x(x), piece_y(y), &SPACE_EDGE.to_string());
 
-            if let Some(index) = get_board_index(x as usize, y as usize) {
+            let index = get_board_index(x as usize, y as usize).map(|i| view_index(i, flipped));
+
+            if let Some(index) = index {
 
                 let i = index as i32;
-                if let Some(piece) = state.board[index] {
-                    if piece.is_player() {
-                        match state.turn {
+                if let Some(piece) = board[index] {
+                    if piece.colour() == Some(game.to_move) || piece == WindSpirit {
+                        match turn {
                             SelectedCard(card) => {
                                 if do_piece_button(
                                     platform,
-                                    &mut state.ui_context,
+                                    context,
                                     x,
                                     y,
                                     piece,
-                                    id_offset + i,
+                                    400 + i,
                                     left_mouse_pressed,
                                     left_mouse_released,
                                 )
@@ -758,6 +1899,104 @@ fn show_pieces(
 const PIECE_BUTTON_WIDTH: i32 = 9;
 const PIECE_BUTTON_HEIGHT: i32 = 5;
 
+//Where the piece on `source_index` can go with the player's card. For wind
+//cards that is only where the piece goes, the spirit comes after.
+fn piece_targets(game: &GameState, pair_index: PairIndex, source_index: usize) -> Vec<usize> {
+    let mut targets = Vec::new();
+
+    for ply in game.legal_plies() {
+        let target_index = match ply {
+            Ply::Move(m, p) if p == pair_index && m.source_index == source_index => m.target_index,
+            Ply::Wind(w, p) if p == pair_index && w.piece.source_index == source_index => {
+                w.piece.target_index
+            }
+            _ => continue,
+        };
+
+        if !targets.contains(&target_index) {
+            targets.push(target_index);
+        }
+    }
+
+    targets
+}
+
+fn spirit_targets(game: &GameState, pair_index: PairIndex, piece: Move) -> Vec<usize> {
+    game.legal_plies()
+        .into_iter()
+        .filter_map(|ply| match ply {
+            Ply::Wind(w, p) if p == pair_index && w.piece == piece => Some(w.spirit.target_index),
+            _ => None,
+        })
+        .collect()
+}
+
+//Highlights each of the squares in `colour` and returns the one that was
+//clicked, if any.
+fn do_target_buttons(
+    platform: &Platform,
+    context: &mut UIContext,
+    targets: &[usize],
+    colour: PieceColour,
+    flipped: bool,
+    left_mouse_pressed: bool,
+    left_mouse_released: bool,
+) -> Option<usize> {
+    let mut result = None;
+
+    for (counter, &target_index) in targets.iter().enumerate() {
+        let (x, y) = match screen_xy(target_index, flipped) {
+            Some(xy) => xy,
+            None => continue,
+        };
+
+        if do_blank_button(
+            platform,
+            context,
+            &BlankButtonSpec {
+                x: piece_x(x) - 4,
+                y: piece_y(y) - 2,
+                w: PIECE_BUTTON_WIDTH,
+                h: PIECE_BUTTON_HEIGHT,
+                id: 450 + counter as UiId,
+            },
+            left_mouse_pressed,
+            left_mouse_released,
+        ) && result.is_none()
+        {
+            result = Some(target_index);
+        }
+
+        with_layer!(platform, 3, {
+            (platform.print_xy)(piece_x(x), piece_y(y), &highlight_char(colour).to_string());
+        })
+    }
+
+    result
+}
+
+//Turning the board around maps each square to the one opposite it, so this
+//goes both from the board to the screen and back.
+fn view_index(index: usize, flipped: bool) -> usize {
+    if flipped {
+        24usize.wrapping_sub(index)
+    } else {
+        index
+    }
+}
+
+//Where on the board grid square `index` is drawn.
+fn screen_xy(index: usize, flipped: bool) -> Option<(i32, i32)> {
+    get_board_xy(view_index(index, flipped)).map(|(x, y)| (x as i32, y as i32))
+}
+
+fn highlight_char(colour: PieceColour) -> char {
+    match colour {
+        Blue => BLUE_HIGHLIGHT,
+        Red => RED_HIGHLIGHT,
+    }
+}
+
 fn do_piece_button(
     platform: &Platform,
     context: &mut UIContext,
@@ -793,6 +2032,7 @@ fn do_card_button(
     x: i32,
     y: i32,
     card: &Card,
+    rotate: bool,
     id: UiId,
     left_mouse_pressed: bool,
     left_mouse_released: bool,
@@ -811,7 +2051,7 @@ fn do_card_button(
         left_mouse_released,
     );
 
-    place_card_tile(platform, x, y, card, false);
+    place_card_tile(platform, x, y, card, rotate);
 
     result
 }
@@ -825,9 +2065,46 @@ fn print_card(platform: &Platform, x: i32, y: i32, card: &Card, rotate: bool) {
 }
 
 fn place_card_tile(platform: &Platform, x: i32, y: i32, card: &Card, rotate: bool) {
-    with_layer!(platform, 1, {
-        (platform.print_xy_offset)(x + 15, y + 3, 0, 7, card.as_str(rotate));
-    });
+    match card.as_str(rotate) {
+        Some(tile) => {
+            with_layer!(platform, 1, {
+                (platform.print_xy_offset)(x + 15, y + 3, 0, 7, tile);
+            });
+        }
+        None => print_card_text(platform, x, y, card, rotate),
+    }
+}
+
+//For cards without a picture in cards.png: the name and stamp on the left and
+//the moves on a 5x5 grid on the right, with the piece in the middle.
+fn print_card_text(platform: &Platform, x: i32, y: i32, card: &Card, rotate: bool) {
+    (platform.print_xy)(x + 2, y + 2, card.name());
+    (platform.print_xy)(x + 2, y + 4, &format!("{} stamp", card.stamp()));
+
+    for grid_y in -2..3 {
+        let mut row = String::new();
+
+        for grid_x in -2..3 {
+            let offset = if rotate {
+                (-grid_x, -grid_y)
+            } else {
+                (grid_x, grid_y)
+            };
+
+            row.push(if offset == (0, 0) {
+                'o'
+            } else if card.offsets().contains(&offset) {
+                'X'
+            } else if card.spirit_offsets().contains(&offset) {
+                WIND_SPIRIT
+            } else {
+                '.'
+            });
+            row.push(' ');
+        }
+
+        (platform.print_xy)(x + 20, y + 3 + grid_y as i32, row.trim_end());
+    }
 }
 
 fn print_piece_xy(platform: &Platform, x: i32, y: i32, s: &str) {
@@ -849,6 +2126,7 @@ fn piece_char(piece: Piece) -> char {
         BlueStudent => STUDENT_BLUE,
         RedMaster => MASTER_RED,
         BlueMaster => MASTER_BLUE,
+        WindSpirit => WIND_SPIRIT,
     }
 }
 
@@ -859,6 +2137,9 @@ const STUDENT_BLUE: char = '\u{E003}';
 const MASTER_BLUE: char = '\u{E004}';
 const PAGODA_BLUE: char = '\u{E005}';
 
+//there is no tile for the Wind Spirit, so it is drawn with a plain letter
+const WIND_SPIRIT: char = 'W';
+
 const SPACE_EDGE: char = '\u{E006}';
 const BLUE_HIGHLIGHT: char = '\u{E007}';
 const RED_HIGHLIGHT: char = '\u{E008}';
@@ -900,28 +2181,15 @@ fn do_blank_button(
     left_mouse_pressed: bool,
     left_mouse_released: bool,
 ) -> bool {
-    let mut result = false;
-
-    let mouse_pos = (platform.mouse_position)();
-    let inside = inside_rect(mouse_pos, spec.x, spec.y, spec.w, spec.h);
+    let result = button_interaction(
+        platform,
+        context,
+        spec,
+        left_mouse_pressed,
+        left_mouse_released,
+    );
     let id = spec.id;
 
-    if context.active == id {
-        if left_mouse_released {
-            result = context.hot == id && inside;
-
-            context.set_not_active();
-        }
-    } else if context.hot == id {
-        if left_mouse_pressed {
-            context.set_active(id);
-        }
-    }
-
-    if inside {
-        context.set_next_hot(id);
-    }
-
     if context.active == id && (platform.key_pressed)(KeyCode::MouseLeft) {
         draw_rect_with(
             platform,
@@ -947,6 +2215,73 @@ fn do_blank_button(
     result
 }
 
+//A single line of text with no border, marked with ">" when `selected` and "-"
+//when hovered over.
+fn do_text_button(
+    platform: &Platform,
+    context: &mut UIContext,
+    spec: &ButtonSpec,
+    selected: bool,
+    left_mouse_pressed: bool,
+    left_mouse_released: bool,
+) -> bool {
+    let base = &spec.base;
+
+    let result = button_interaction(
+        platform,
+        context,
+        base,
+        left_mouse_pressed,
+        left_mouse_released,
+    );
+
+    let marker = if selected {
+        ">"
+    } else if context.hot == base.id {
+        "-"
+    } else {
+        " "
+    };
+
+    (platform.print_xy)(base.x, base.y, &format!("{}{}", marker, spec.text));
+
+    result
+}
+
+//the hot and active bookkeeping that every kind of button needs. Returns true
+//if the button was clicked.
+fn button_interaction(
+    platform: &Platform,
+    context: &mut UIContext,
+    spec: &BlankButtonSpec,
+    left_mouse_pressed: bool,
+    left_mouse_released: bool,
+) -> bool {
+    let mut result = false;
+
+    let mouse_pos = (platform.mouse_position)();
+    let inside = inside_rect(mouse_pos, spec.x, spec.y, spec.w, spec.h);
+    let id = spec.id;
+
+    if context.active == id {
+        if left_mouse_released {
+            result = context.hot == id && inside;
+
+            context.set_not_active();
+        }
+    } else if context.hot == id {
+        if left_mouse_pressed {
+            context.set_active(id);
+        }
+    }
+
+    if inside {
+        context.set_next_hot(id);
+    }
+
+    result
+}
+
 fn do_button(
     platform: &Platform,
     context: &mut UIContext,